@@ -1,22 +1,81 @@
 // Copyright (c) 2023 d-k-bo
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-//! Build a simplified abstract syntax tree from [`pulldown_cmark::Event`]s.
+//! A simplified abstract syntax tree of markdown, which only distinguishes the elements that are
+//! relevant for recipes.
+//!
+//! This is the tree the recipe parser is built on. It can be used to recognize additional
+//! constructs in a recipe without reimplementing the markdown parsing.
+//!
+//! ```
+//! # use recipemd::ast::{self, NodeKind};
+//! let src = "# Cake\n\n> Source: grandma\n\n---\n\n- *200 g* flour\n";
+//! let nodes: Vec<_> = ast::parse(src).collect();
+//!
+//! assert!(matches!(nodes[0].kind, NodeKind::Heading { level: 1, .. }));
+//! assert_eq!(&src[nodes[1].span.clone()], "> Source: grandma\n");
+//! assert!(matches!(nodes[1].kind, NodeKind::Other));
+//! assert!(matches!(nodes[2].kind, NodeKind::HorizontalLine));
+//!
+//! let NodeKind::List(items) = &nodes[3].kind else { panic!() };
+//! assert!(matches!(items[0].children()[0].kind, NodeKind::Emphasis(_)));
+//! ```
 
-use std::ops::Range;
+use std::{borrow::Cow, ops::Range};
 
-use pulldown_cmark::{CowStr, Event, HeadingLevel, Tag};
+use pulldown_cmark::{CowStr, Event, MetadataBlockKind, Tag};
 
 use crate::parser::RecipeParser;
 
-/// Represents a node in the abstract syntax tree.
-#[derive(Clone, Debug)]
-#[cfg_attr(test, derive(PartialEq))]
-pub(crate) struct Node<'s> {
+/// Returns an iterator over the top level nodes of a markdown document.
+///
+/// The document is parsed like a recipe, i.e. front matter is recognized if the `front-matter`
+/// feature is enabled.
+pub fn parse(src: &str) -> Nodes<'_> {
+    Nodes(RecipeParser::new(src))
+}
+
+/// An iterator over the top level nodes of a markdown document, see [`parse`].
+pub struct Nodes<'s>(RecipeParser<'s>);
+
+impl<'s> Iterator for Nodes<'s> {
+    type Item = Node<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.parse_node()
+    }
+}
+
+impl std::fmt::Debug for Nodes<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Nodes").field("pos", &self.0.pos).finish()
+    }
+}
+
+/// A node in the abstract syntax tree, together with its byte range in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node<'s> {
     pub kind: NodeKind<'s>,
     pub span: Range<usize>,
 }
 
+impl<'s> Node<'s> {
+    /// Returns the child nodes, which are empty for text, horizontal lines and other nodes.
+    pub fn children(&self) -> &[Node<'s>] {
+        match &self.kind {
+            NodeKind::Heading { children, .. }
+            | NodeKind::Paragraph(children)
+            | NodeKind::Emphasis(children)
+            | NodeKind::Strong(children)
+            | NodeKind::List(children)
+            | NodeKind::ListItem(children)
+            | NodeKind::MetadataBlock { children, .. }
+            | NodeKind::Link { children, .. } => children,
+            NodeKind::HorizontalLine | NodeKind::Text(_) | NodeKind::Other => &[],
+        }
+    }
+}
+
 impl Node<'_> {
     /// Recursively replaces all paragraphs its children
     pub(crate) fn flatten_paragraphs(&self) -> Self {
@@ -51,28 +110,70 @@ impl Node<'_> {
     }
 }
 
-/// The simplified type of node if it is relevant for parsing recipe.
-#[derive(Clone, Debug)]
-#[cfg_attr(test, derive(PartialEq))]
-pub(crate) enum NodeKind<'s> {
+/// The type of a [`Node`].
+///
+/// More kinds may be added in the future, which are then no longer reported as
+/// [`Other`](NodeKind::Other).
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum NodeKind<'s> {
+    /// An ATX (`#`) or setext (`===`) heading.
     Heading {
-        level: HeadingLevel,
+        /// The level of the heading, from 1 to 6.
+        level: u8,
+        /// The inline content of the heading.
         children: Vec<Node<'s>>,
     },
+    /// A paragraph, whose children are inline nodes.
     Paragraph(Vec<Node<'s>>),
+    /// Text in `*` or `_`.
     Emphasis(Vec<Node<'s>>),
+    /// Text in `**` or `__`.
     Strong(Vec<Node<'s>>),
+    /// An ordered or unordered list, whose children are [list items](NodeKind::ListItem).
     List(Vec<Node<'s>>),
+    /// An item of a list. Its children are inline nodes for tight lists and paragraphs for
+    /// loose lists, followed by nested lists.
     ListItem(Vec<Node<'s>>),
+    /// A thematic break like `---` or `***`.
     HorizontalLine,
-    Text(CowStr<'s>),
+    /// A front matter block, whose child is the text of its content.
+    MetadataBlock {
+        /// Whether the block contains YAML or TOML.
+        kind: MetadataKind,
+        /// The [text](NodeKind::Text) of the block, without the surrounding `---` or `+++` lines.
+        children: Vec<Node<'s>>,
+    },
+    /// Text with escapes and entities resolved.
+    Text(Cow<'s, str>),
+    /// A link like `[text](destination)`, including reference links and autolinks.
     Link {
-        destination: CowStr<'s>,
+        /// The URL the link points to.
+        destination: Cow<'s, str>,
+        /// The inline content of the link text.
         children: Vec<Node<'s>>,
     },
+    /// Any other markdown element like block quotes, code, images, HTML and line breaks.
     Other,
 }
 
+/// The syntax of a front matter block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MetadataKind {
+    /// YAML between `---` lines.
+    Yaml,
+    /// TOML between `+++` lines.
+    Toml,
+}
+
+fn cow_str(s: CowStr<'_>) -> Cow<'_, str> {
+    match s {
+        CowStr::Borrowed(s) => Cow::Borrowed(s),
+        s => Cow::Owned(s.to_string()),
+    }
+}
+
 pub(crate) trait NodeList {
     fn span(&self) -> Range<usize>;
 }
@@ -92,7 +193,7 @@ impl NodeList for [Node<'_>] {
 impl<'s> RecipeParser<'s> {
     /// Consume events of the underlying parser until a complete [`Node`] can be returned.
     /// Returns `None` when the parser reaches its end.
-    pub fn parse_node(&mut self) -> Option<Node<'s>> {
+    pub(crate) fn parse_node(&mut self) -> Option<Node<'s>> {
         if let Event::End(_) = self.parser.peek()?.0 {
             return None;
         }
@@ -103,7 +204,10 @@ impl<'s> RecipeParser<'s> {
                     let (children, end) = self.parse_child_nodes();
 
                     Node {
-                        kind: NodeKind::Heading { level, children },
+                        kind: NodeKind::Heading {
+                            level: level as u8,
+                            children,
+                        },
                         span: start..end,
                     }
                 }
@@ -142,6 +246,19 @@ impl<'s> RecipeParser<'s> {
                         span: start..end,
                     }
                 }
+                Tag::MetadataBlock(kind) => {
+                    let (children, end) = self.parse_child_nodes();
+                    Node {
+                        kind: NodeKind::MetadataBlock {
+                            kind: match kind {
+                                MetadataBlockKind::YamlStyle => MetadataKind::Yaml,
+                                MetadataBlockKind::PlusesStyle => MetadataKind::Toml,
+                            },
+                            children,
+                        },
+                        span: start..end,
+                    }
+                }
                 Tag::Link {
                     dest_url: destination,
                     ..
@@ -150,7 +267,7 @@ impl<'s> RecipeParser<'s> {
 
                     Node {
                         kind: NodeKind::Link {
-                            destination,
+                            destination: cow_str(destination),
                             children,
                         },
                         span: start..end,
@@ -169,7 +286,7 @@ impl<'s> RecipeParser<'s> {
                 span,
             },
             (Event::Text(text), span) => Node {
-                kind: NodeKind::Text(text),
+                kind: NodeKind::Text(cow_str(text)),
                 span,
             },
             (_, span) => Node {
@@ -204,7 +321,7 @@ mod tests {
             RecipeParser::new("# A recipe title\n").parse_node(),
             Some(Node {
                 kind: NodeKind::Heading {
-                    level: HeadingLevel::H1,
+                    level: 1,
                     children: vec![Node {
                         kind: NodeKind::Text("A recipe title".into()),
                         span: 2..16
@@ -0,0 +1,575 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use lazy_regex::regex;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, CompletionTextEdit,
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams, Location,
+    MarkupContent, MarkupKind, Range, SymbolKind, TextEdit,
+};
+use recipemd::{units::Unit, Amount, Factor, Ingredient};
+
+use crate::workspace::{path_to_uri, Document, Workspace};
+
+const SOURCE: &str = "recipemd";
+
+/// Returns parse errors, lints and broken links of a document.
+pub fn diagnostics(workspace: &Workspace, document: &Document) -> Vec<Diagnostic> {
+    let diagnostic = |span, severity, message| Diagnostic {
+        range: document.range(span),
+        severity: Some(severity),
+        source: Some(SOURCE.to_owned()),
+        message,
+        ..Default::default()
+    };
+    let inner = &document.inner;
+    let mut diagnostics = Vec::new();
+
+    if let Some(error) = inner.error() {
+        let span = error
+            .span
+            .clone()
+            .unwrap_or(document.src().len()..document.src().len());
+        diagnostics.push(diagnostic(
+            span,
+            DiagnosticSeverity::ERROR,
+            error.kind.to_string(),
+        ));
+    }
+    for lint in inner.lints() {
+        diagnostics.push(diagnostic(
+            lint.span,
+            DiagnosticSeverity::WARNING,
+            lint.kind.to_string(),
+        ));
+    }
+
+    if let (Some(recipe), Some(path)) = (inner.recipe(), &document.path) {
+        let links = recipe
+            .all_ingredients()
+            .zip(&inner.source_map().ingredients)
+            .filter_map(|(ingredient, spans)| {
+                Some((ingredient.link.as_deref()?, spans.link.clone()?))
+            });
+        for (link, span) in links {
+            let in_workspace = workspace.collection(path).is_some();
+            if in_workspace && is_local_link(link) && workspace.resolve_link(path, link).is_none() {
+                diagnostics.push(diagnostic(
+                    span,
+                    DiagnosticSeverity::WARNING,
+                    format!("`{link}` is not a recipe of the workspace"),
+                ));
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// Shows the parsed amount of an ingredient or yield, and how it scales.
+pub fn hover(workspace: &Workspace, params: HoverParams) -> Option<Hover> {
+    let position = params.text_document_position_params;
+    let document = workspace.document(&position.text_document.uri)?;
+    let offset = document.offset(position.position);
+    let inner = &document.inner;
+    let recipe = inner.recipe()?;
+
+    let (text, span) = if let Some((ingredient, spans)) = inner.ingredient_at(offset) {
+        (
+            ingredient_hover(ingredient, &recipe.yields),
+            spans.span.clone(),
+        )
+    } else if let Some((amount, span)) = inner.yield_at(offset) {
+        let index = recipe
+            .yields
+            .iter()
+            .position(|other| std::ptr::eq(other, amount))?;
+        (yield_hover(index, &recipe.yields), span.clone())
+    } else {
+        return None;
+    };
+
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: text,
+        }),
+        range: Some(document.range(span)),
+    })
+}
+
+fn ingredient_hover(ingredient: &Ingredient, yields: &[Amount]) -> String {
+    let mut text = format!("**{}**", ingredient.name);
+    if let Some(note) = &ingredient.note {
+        text.push_str(&format!(", {note}"));
+    }
+    for alternative in &ingredient.alternatives {
+        text.push_str(&format!("\n\nor {}", alternative.name));
+    }
+    let Some(amount) = &ingredient.amount else {
+        return text;
+    };
+
+    text.push_str("\n\n");
+    text.push_str(&describe_amount(amount));
+    if let Some(metric) = to_metric(amount) {
+        text.push_str(&format!("\n- ≈ {metric}"));
+    }
+    for per in yields {
+        let factor = f32::from(per.factor);
+        if factor > 0.0 && factor != 1.0 {
+            text.push_str(&format!(
+                "\n- {} for 1 of {per}",
+                round(&amount.scale(1.0 / factor))
+            ));
+        }
+    }
+    text
+}
+
+fn yield_hover(index: usize, yields: &[Amount]) -> String {
+    let amount = &yields[index];
+    let mut text = format!("**Yield**\n\n{}", describe_amount(amount));
+    let factor = f32::from(amount.factor);
+    if factor > 0.0 {
+        let others = yields.iter().enumerate().filter(|(i, _)| *i != index);
+        for (_, other) in others.filter(|_| factor != 1.0) {
+            text.push_str(&format!(
+                "\n- {} for 1 of {amount}",
+                round(&other.scale(1.0 / factor))
+            ));
+        }
+        for multiple in [2.0, 0.5] {
+            text.push_str(&format!(
+                "\n- ×{multiple}: {}",
+                round(&amount.scale(multiple))
+            ));
+        }
+    }
+    text
+}
+
+fn describe_amount(amount: &Amount) -> String {
+    let factor = match amount.factor {
+        Factor::Integer(value) => format!("integer `{value}`"),
+        Factor::Fraction(numerator, denominator) => {
+            format!("fraction `{numerator}/{denominator}`")
+        }
+        Factor::Float(value) => format!("decimal `{value}`"),
+    };
+    match &amount.unit {
+        Some(unit) => format!("- {factor}, unit `{unit}`"),
+        None => format!("- {factor}, no unit"),
+    }
+}
+
+/// Converts an amount with a non-metric unit to grams or milliliters.
+fn to_metric(amount: &Amount) -> Option<Amount> {
+    let unit = Unit::lookup(amount.unit.as_deref()?)?;
+    let metric = match unit.dimension {
+        recipemd::units::Dimension::Mass => "g",
+        recipemd::units::Dimension::Volume => "ml",
+    };
+    match unit.symbol {
+        "mg" | "g" | "kg" | "ml" | "cl" | "dl" | "l" => None,
+        _ => amount.convert_to(Some(metric)).map(|amount| round(&amount)),
+    }
+}
+
+/// Rounds decimal amounts to two decimal places.
+fn round(amount: &Amount) -> Amount {
+    let factor = match amount.factor {
+        Factor::Float(value) => {
+            let rounded = (value * 100.0).round() / 100.0;
+            match rounded.fract() == 0.0 && rounded >= 0.0 {
+                true => Factor::Integer(rounded as u32),
+                false => Factor::Float(rounded),
+            }
+        }
+        factor => factor,
+    };
+    Amount {
+        factor,
+        unit: amount.unit.clone(),
+    }
+}
+
+/// Jumps to the recipe an ingredient links to.
+pub fn definition(
+    workspace: &Workspace,
+    params: GotoDefinitionParams,
+) -> Option<GotoDefinitionResponse> {
+    let position = params.text_document_position_params;
+    let document = workspace.document(&position.text_document.uri)?;
+    let (ingredient, _) = document
+        .inner
+        .ingredient_at(document.offset(position.position))?;
+
+    let path = workspace.resolve_file(document.path.as_ref()?, ingredient.link.as_deref()?)?;
+    Some(GotoDefinitionResponse::Scalar(Location::new(
+        path_to_uri(&path)?,
+        Range::default(),
+    )))
+}
+
+/// Lists the title and the ingredient groups, nested by heading level.
+pub fn document_symbols(
+    workspace: &Workspace,
+    params: DocumentSymbolParams,
+) -> Option<DocumentSymbolResponse> {
+    let document = workspace.document(&params.text_document.uri)?;
+    let source_map = document.inner.source_map();
+    let src = document.src();
+
+    #[allow(deprecated)]
+    let symbol = |name: &str, kind, span, selection_span| DocumentSymbol {
+        name: name.to_owned(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: document.range(span),
+        selection_range: document.range(selection_span),
+        children: None,
+    };
+
+    // the groups are ordered depth-first, so a stack of the currently open groups suffices
+    let mut stack: Vec<(u8, DocumentSymbol)> = Vec::new();
+    let mut groups = Vec::new();
+    let close = |stack: &mut Vec<(u8, DocumentSymbol)>, groups: &mut Vec<DocumentSymbol>| {
+        let (_, symbol) = stack.pop().expect("stack is not empty");
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.get_or_insert_with(Vec::new).push(symbol),
+            None => groups.push(symbol),
+        }
+    };
+    for group in &source_map.ingredient_groups {
+        while stack.last().is_some_and(|(level, _)| *level >= group.level) {
+            close(&mut stack, &mut groups);
+        }
+        stack.push((
+            group.level,
+            symbol(
+                &src[group.title.clone()],
+                SymbolKind::NAMESPACE,
+                group.span.clone(),
+                group.title.clone(),
+            ),
+        ));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut groups);
+    }
+
+    let symbols = match &source_map.title {
+        Some(title) => {
+            let mut symbol = symbol(
+                &src[title.clone()],
+                SymbolKind::FILE,
+                0..src.len(),
+                title.clone(),
+            );
+            symbol.children = Some(groups);
+            vec![symbol]
+        }
+        None => groups,
+    };
+    Some(DocumentSymbolResponse::Nested(symbols))
+}
+
+/// Completes units in amounts and yields, and tags in the tags paragraph.
+pub fn completion(workspace: &Workspace, params: CompletionParams) -> Option<CompletionResponse> {
+    let position = params.text_document_position;
+    let document = workspace.document(&position.text_document.uri)?;
+    let offset = document.offset(position.position);
+    let line_start = document.src()[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &document.src()[line_start..offset];
+
+    let amount = regex!(
+        r"^(?:\s*(?:[-*+]|\d+[.)])\s+\*|\*\*(?:[^*]*,)?)\s*[\d¼-¾⅐-⅞][\d\s.,/¼-¾⅐-⅞]*?\s*(?P<prefix>[^\d\s*,][^*,]*)?$"
+    );
+    let tags = regex!(r"^\*(?:[^*]*,)?\s*(?P<prefix>[^*,]*)$");
+
+    let (prefix, kind, labels): (_, _, Vec<String>) = if let Some(m) = amount.captures(line) {
+        let units = Unit::all()
+            .map(|unit| unit.symbol)
+            .chain(workspace.units())
+            .collect::<std::collections::BTreeSet<_>>();
+        (
+            m.name("prefix").map_or("", |m| m.as_str()),
+            CompletionItemKind::UNIT,
+            units.into_iter().map(ToOwned::to_owned).collect(),
+        )
+    } else if let Some(m) = tags
+        .captures(line)
+        .filter(|_| before_ingredients(document, offset))
+    {
+        (
+            m.name("prefix").map_or("", |m| m.as_str()),
+            CompletionItemKind::KEYWORD,
+            workspace
+                .tags(document.path.as_deref())
+                .into_iter()
+                .map(ToOwned::to_owned)
+                .collect(),
+        )
+    } else {
+        return None;
+    };
+
+    let range = document.range(offset - prefix.len()..offset);
+    Some(CompletionResponse::Array(
+        labels
+            .into_iter()
+            .map(|label| CompletionItem {
+                kind: Some(kind),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                    range,
+                    label.clone(),
+                ))),
+                label,
+                ..Default::default()
+            })
+            .collect(),
+    ))
+}
+
+/// Returns `true` if the offset is before the first horizontal line, where tags are written.
+fn before_ingredients(document: &Document, offset: usize) -> bool {
+    match regex!(r"(?m)^ {0,3}(?:(?:-\s*){3,}|(?:\*\s*){3,}|(?:_\s*){3,})$").find(document.src()) {
+        Some(m) => offset < m.start(),
+        None => true,
+    }
+}
+
+/// Returns `true` if a link may point to a local recipe file.
+fn is_local_link(link: &str) -> bool {
+    !(link.is_empty()
+        || link.starts_with('#')
+        || link.contains("://")
+        || link.starts_with("mailto:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{
+        Position, TextDocumentIdentifier, TextDocumentPositionParams, Uri, WorkDoneProgressParams,
+    };
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const SRC: &str = "# Cake\n\n**1 cake, 12 pieces**\n\n---\n\n- *2 cups* flour\n\n## Topping\n\n### Fruit\n\n- *3* cherries\n\n## Glaze\n\n- *100 \n";
+
+    fn workspace() -> (Workspace, Uri) {
+        let uri: Uri = "untitled:cake.md".parse().unwrap();
+        let mut workspace = Workspace::default();
+        workspace.update(uri.clone(), SRC.to_owned());
+        (workspace, uri)
+    }
+
+    fn position(uri: &Uri, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri.clone()),
+            Position::new(line, character),
+        )
+    }
+
+    #[test]
+    fn hover_amounts() {
+        let src = "# Cake\n\n**1 cake, 12 pieces**\n\n---\n\n- *2 cups* flour\n";
+        let uri: Uri = "untitled:cake.md".parse().unwrap();
+        let mut workspace = Workspace::default();
+        workspace.update(uri.clone(), src.to_owned());
+
+        let hover = |line, character| {
+            let hover = super::hover(
+                &workspace,
+                HoverParams {
+                    text_document_position_params: position(&uri, line, character),
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                },
+            );
+            match hover.map(|hover| hover.contents) {
+                Some(HoverContents::Markup(content)) => Some(content.value),
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            hover(6, 4).as_deref(),
+            Some(
+                "**flour**\n\n- integer `2`, unit `cups`\n- ≈ 473.18 ml\n- 0.17 cups for 1 of 12 pieces"
+            )
+        );
+        assert_eq!(
+            hover(2, 3).as_deref(),
+            Some("**Yield**\n\n- integer `1`, unit `cake`\n- ×2: 2 cake\n- ×0.5: 0.5 cake")
+        );
+        assert_eq!(
+            hover(2, 12).as_deref(),
+            Some("**Yield**\n\n- integer `12`, unit `pieces`\n- 0.08 cake for 1 of 12 pieces\n- ×2: 24 pieces\n- ×0.5: 6 pieces")
+        );
+        assert_eq!(hover(0, 2), None);
+    }
+
+    #[test]
+    fn nested_symbols() {
+        let (workspace, uri) = workspace();
+        let Some(DocumentSymbolResponse::Nested(symbols)) = document_symbols(
+            &workspace,
+            DocumentSymbolParams {
+                text_document: TextDocumentIdentifier::new(uri),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        ) else {
+            panic!("expected nested symbols")
+        };
+
+        fn names(symbols: &[DocumentSymbol]) -> Vec<String> {
+            symbols
+                .iter()
+                .map(|symbol| match &symbol.children {
+                    Some(children) if !children.is_empty() => {
+                        format!("{} [{}]", symbol.name, names(children).join(", "))
+                    }
+                    _ => symbol.name.clone(),
+                })
+                .collect()
+        }
+        assert_eq!(names(&symbols), ["Cake [Topping [Fruit], Glaze]"]);
+        assert_eq!(
+            symbols[0].children.as_ref().unwrap()[0].range,
+            Range::new(Position::new(8, 0), Position::new(12, 14))
+        );
+    }
+
+    #[test]
+    fn complete_units() {
+        let (workspace, uri) = workspace();
+        let complete = |line, character| {
+            let response = completion(
+                &workspace,
+                CompletionParams {
+                    text_document_position: position(&uri, line, character),
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                    context: None,
+                },
+            );
+            match response {
+                Some(CompletionResponse::Array(items)) => {
+                    items.into_iter().map(|item| item.label).collect()
+                }
+                _ => Vec::new(),
+            }
+        };
+
+        let units = complete(16, 7);
+        assert!(units.contains(&"tbsp".to_owned()));
+        assert!(units.contains(&"kg".to_owned()));
+        assert!(complete(6, 5).contains(&"g".to_owned()));
+        assert!(complete(2, 4).contains(&"ml".to_owned()));
+        assert_eq!(complete(6, 12), Vec::<String>::new());
+    }
+
+    #[test]
+    fn report_errors() {
+        let uri: Uri = "untitled:cake.md".parse().unwrap();
+        let mut workspace = Workspace::default();
+        let messages = |workspace: &Workspace| {
+            diagnostics(workspace, workspace.document(&uri).unwrap())
+                .into_iter()
+                .map(|diagnostic| (diagnostic.range.start, diagnostic.message))
+                .collect::<Vec<_>>()
+        };
+
+        workspace.update(uri.clone(), "# Cake\n\n---\n\n- *1*\n".to_owned());
+        assert_eq!(
+            messages(&workspace),
+            [(
+                Position::new(4, 0),
+                "ingredient is missing a name".to_owned()
+            )]
+        );
+
+        workspace.update(
+            uri.clone(),
+            "# Cake\n\n---\n\n- flour\n- Flour\n".to_owned(),
+        );
+        assert_eq!(
+            messages(&workspace),
+            [(
+                Position::new(5, 0),
+                "ingredient `Flour` is listed more than once in the same group".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn workspace_links_and_tags() {
+        let root = std::env::temp_dir().join(format!("recipemd-lsp-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("basics")).unwrap();
+        std::fs::write(
+            root.join("basics/dough.md"),
+            "# Dough\n\n*vegan, basic*\n\n---\n\n- flour\n",
+        )
+        .unwrap();
+
+        let mut workspace = Workspace::new([root.clone()]);
+        let uri = path_to_uri(&root.join("pizza.md")).unwrap();
+        workspace.update(
+            uri.clone(),
+            "# Pizza\n\n*ve*\n\n---\n\n- [dough](basics/dough.md)\n- [sauce](sauce.md)\n"
+                .to_owned(),
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let document = workspace.document(&uri).unwrap();
+        let diagnostics = diagnostics(&workspace, document);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "`sauce.md` is not a recipe of the workspace"
+        );
+
+        let Some(GotoDefinitionResponse::Scalar(location)) = definition(
+            &workspace,
+            GotoDefinitionParams {
+                text_document_position_params: position(&uri, 6, 4),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            },
+        ) else {
+            panic!("expected a location")
+        };
+        assert_eq!(
+            location.uri,
+            path_to_uri(&root.join("basics/dough.md")).unwrap()
+        );
+
+        let Some(CompletionResponse::Array(items)) = completion(
+            &workspace,
+            CompletionParams {
+                text_document_position: position(&uri, 2, 3),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+                context: None,
+            },
+        ) else {
+            panic!("expected completions")
+        };
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, ["basic", "vegan"]);
+        assert_eq!(
+            items[0].text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(2, 1), Position::new(2, 3)),
+                "basic".to_owned()
+            )))
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use std::ops::Range;
+
+use lsp_types::Position;
+
+/// Converts between byte offsets and the line/UTF-16 column positions used by LSP.
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(src: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { line_starts }
+    }
+
+    pub fn position(&self, src: &str, offset: usize) -> Position {
+        let mut offset = offset.min(src.len());
+        while !src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character = src[self.line_starts[line]..offset].encode_utf16().count();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    /// Returns the byte offset of a position, clamped to the end of its line.
+    pub fn offset(&self, src: &str, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return src.len();
+        };
+        let line = src[start..]
+            .split_inclusive('\n')
+            .next()
+            .unwrap_or_default();
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        let mut character = 0;
+        for (i, c) in line.char_indices() {
+            if character >= position.character as usize {
+                return start + i;
+            }
+            character += c.len_utf16();
+        }
+        start + line.len()
+    }
+
+    pub fn range(&self, src: &str, span: Range<usize>) -> lsp_types::Range {
+        lsp_types::Range::new(self.position(src, span.start), self.position(src, span.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn convert_positions() {
+        let src = "# Äpfel\r\n\n- *½ kg* 🍎s\n";
+        let index = LineIndex::new(src);
+
+        let offset = src.find("kg").unwrap();
+        assert_eq!(index.position(src, offset), Position::new(2, 5));
+        assert_eq!(index.offset(src, Position::new(2, 5)), offset);
+
+        let offset = src.find('s').unwrap();
+        assert_eq!(index.position(src, offset), Position::new(2, 11));
+        assert_eq!(index.offset(src, Position::new(2, 11)), offset);
+
+        assert_eq!(index.offset(src, Position::new(0, 100)), "# Äpfel".len());
+        assert_eq!(index.offset(src, Position::new(9, 0)), src.len());
+        assert_eq!(index.position(src, src.len()), Position::new(3, 0));
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Language server for RecipeMD files.
+//!
+//! The server communicates over stdin and stdout and provides
+//!
+//! - diagnostics for parse errors, lints and links to missing recipes,
+//! - hover information for amounts and yields,
+//! - go to definition for linked ingredients,
+//! - document symbols for the title and ingredient groups and
+//! - completion of units and of the tags used in the workspace.
+
+mod handlers;
+mod line_index;
+mod workspace;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _},
+    CompletionOptions, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, HoverProviderCapability, InitializeParams, InitializeResult, OneOf,
+    PublishDiagnosticsParams, ServerCapabilities, ServerInfo, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+
+use crate::workspace::{uri_to_path, Workspace};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+fn main() -> Result<(), Error> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let (id, params) = connection.initialize_start()?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let result = InitializeResult {
+        capabilities: capabilities(),
+        server_info: Some(ServerInfo {
+            name: env!("CARGO_BIN_NAME").to_owned(),
+            version: Some(env!("CARGO_PKG_VERSION").to_owned()),
+        }),
+    };
+    connection.initialize_finish(id, serde_json::to_value(result)?)?;
+
+    main_loop(connection, &params)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+/// Handles messages until the client shuts the server down.
+///
+/// Takes ownership of the connection so that the IO threads can finish afterwards.
+fn main_loop(connection: Connection, params: &InitializeParams) -> Result<(), Error> {
+    let mut server = Server {
+        connection: &connection,
+        workspace: Workspace::new(roots(params)),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.handle_request(request)?;
+            }
+            Message::Notification(notification) => server.handle_notification(notification)?,
+            Message::Response(_) => (),
+        }
+    }
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![" ".to_owned(), ",".to_owned()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Returns the directories of the workspace folders.
+#[allow(deprecated)]
+fn roots(params: &InitializeParams) -> Vec<std::path::PathBuf> {
+    match &params.workspace_folders {
+        Some(folders) => folders
+            .iter()
+            .filter_map(|folder| uri_to_path(&folder.uri))
+            .collect(),
+        None => params.root_uri.iter().filter_map(uri_to_path).collect(),
+    }
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    workspace: Workspace,
+}
+
+impl Server<'_> {
+    fn handle_request(&mut self, request: Request) -> Result<(), Error> {
+        let response = match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, handlers::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, handlers::definition),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, handlers::document_symbols)
+            }
+            Completion::METHOD => self.respond::<Completion>(request, handlers::completion),
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown request `{method}`"),
+            ),
+        };
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Workspace, R::Params) -> R::Result,
+    ) -> Response {
+        let id = request.id.clone();
+        match request.extract(R::METHOD) {
+            Ok((id, params)) => Response::new_ok(id, handler(&self.workspace, params)),
+            Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<(), Error> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.workspace
+                    .update(uri.clone(), params.text_document.text);
+                self.publish_diagnostics(uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                for change in params.content_changes {
+                    match change.range {
+                        Some(range) => self.workspace.edit(&uri, range, &change.text),
+                        None => self.workspace.update(uri.clone(), change.text),
+                    }
+                }
+                self.publish_diagnostics(uri)?;
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.workspace.close(&params.text_document.uri);
+                self.send_diagnostics(params.text_document.uri, Vec::new())?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> Result<(), Error> {
+        let diagnostics = match self.workspace.document(&uri) {
+            Some(document) => handlers::diagnostics(&self.workspace, document),
+            None => Vec::new(),
+        };
+        self.send_diagnostics(uri, diagnostics)
+    }
+
+    fn send_diagnostics(
+        &self,
+        uri: Uri,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), Error> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        self.connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                PublishDiagnostics::METHOD.to_owned(),
+                params,
+            )))?;
+        Ok(())
+    }
+}
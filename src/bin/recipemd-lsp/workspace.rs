@@ -0,0 +1,280 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+
+use lsp_types::Uri;
+use recipemd::{
+    collection::{percent_decode, Collection},
+    document::RecipeDocument,
+};
+
+use crate::line_index::LineIndex;
+
+/// An open text document.
+#[derive(Debug)]
+pub struct Document {
+    pub inner: RecipeDocument,
+    pub lines: LineIndex,
+    pub path: Option<PathBuf>,
+}
+
+impl Document {
+    fn new(uri: &Uri, src: String) -> Self {
+        Self {
+            lines: LineIndex::new(&src),
+            inner: RecipeDocument::parse(src),
+            path: uri_to_path(uri),
+        }
+    }
+
+    pub fn src(&self) -> &str {
+        self.inner.src()
+    }
+
+    pub fn range(&self, span: std::ops::Range<usize>) -> lsp_types::Range {
+        self.lines.range(self.src(), span)
+    }
+
+    pub fn offset(&self, position: lsp_types::Position) -> usize {
+        self.lines.offset(self.src(), position)
+    }
+}
+
+/// The open documents and the recipes of all workspace folders.
+///
+/// Open documents replace the files of the same path in the collections, so that completions
+/// and links reflect unsaved changes.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    documents: HashMap<Uri, Document>,
+    collections: Vec<Collection>,
+}
+
+impl Workspace {
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            documents: HashMap::new(),
+            collections: roots
+                .into_iter()
+                .filter_map(|root| Collection::load(root).ok())
+                .collect(),
+        }
+    }
+
+    pub fn document(&self, uri: &Uri) -> Option<&Document> {
+        self.documents.get(uri)
+    }
+
+    pub fn update(&mut self, uri: Uri, src: String) {
+        let document = Document::new(&uri, src);
+        self.sync(&document);
+        self.documents.insert(uri, document);
+    }
+
+    /// Applies a change to an open document, parsing only the changed part again.
+    pub fn edit(&mut self, uri: &Uri, range: lsp_types::Range, text: &str) {
+        let Some(mut document) = self.documents.remove(uri) else {
+            return;
+        };
+        let span = document.offset(range.start)..document.offset(range.end);
+        let _ = document.inner.edit(span, text);
+        document.lines = LineIndex::new(document.src());
+        self.sync(&document);
+        self.documents.insert(uri.clone(), document);
+    }
+
+    /// Replaces the recipe of a document in its collection.
+    fn sync(&mut self, document: &Document) {
+        let Some(path) = &document.path else {
+            return;
+        };
+        if let Some(i) = self.collection_index(path) {
+            let collection = &mut self.collections[i];
+            let relative = path.strip_prefix(collection.root()).unwrap_or(path);
+            match document.inner.recipe() {
+                Some(recipe) => collection.insert_recipe(relative, recipe.clone()),
+                None => collection.insert(relative, document.src()),
+            }
+        }
+    }
+
+    pub fn close(&mut self, uri: &Uri) {
+        let Some(document) = self.documents.remove(uri) else {
+            return;
+        };
+        let Some(path) = document.path else {
+            return;
+        };
+        if let Some(i) = self.collection_index(&path) {
+            // forget unsaved changes
+            let collection = &mut self.collections[i];
+            let relative = path.strip_prefix(collection.root()).unwrap_or(&path);
+            match std::fs::read_to_string(&path) {
+                Ok(src) => collection.insert(relative, &src),
+                Err(_) => {
+                    collection.remove(relative);
+                }
+            }
+        }
+    }
+
+    /// Returns the workspace folder that contains the file and the file's path relative to it.
+    pub fn collection<'a>(&'a self, path: &'a Path) -> Option<(&'a Collection, &'a Path)> {
+        let collection = &self.collections[self.collection_index(path)?];
+        Some((collection, path.strip_prefix(collection.root()).ok()?))
+    }
+
+    /// Returns the absolute path of the recipe a link in the file points to.
+    ///
+    /// Returns `None` if the file is not part of a workspace folder.
+    pub fn resolve_link(&self, from: &Path, link: &str) -> Option<PathBuf> {
+        let (collection, relative) = self.collection(from)?;
+        let entry = collection.resolve_link(relative, link)?;
+        Some(collection.root().join(&entry.path))
+    }
+
+    /// Returns the path of the file a link in the file points to, even if the file is not part
+    /// of a workspace folder or not a valid recipe.
+    pub fn resolve_file(&self, from: &Path, link: &str) -> Option<PathBuf> {
+        if let Some(path) = self.resolve_link(from, link) {
+            return Some(path);
+        }
+        let link = link.split(['#', '?']).next().unwrap_or_default();
+        let path = from.parent()?.join(percent_decode(link));
+        path.is_file().then_some(path)
+    }
+
+    /// Returns the tags of all recipes, except for the one at the given path.
+    pub fn tags(&self, except: Option<&Path>) -> BTreeSet<&str> {
+        self.collections
+            .iter()
+            .flat_map(|collection| {
+                collection
+                    .entries()
+                    .iter()
+                    .filter(move |entry| except != Some(&collection.root().join(&entry.path)))
+            })
+            .flat_map(|entry| &entry.recipe.tags)
+            .map(String::as_str)
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    /// Returns the units of all amounts and yields of all recipes.
+    pub fn units(&self) -> BTreeSet<&str> {
+        self.collections
+            .iter()
+            .flat_map(Collection::entries)
+            .flat_map(|entry| {
+                let recipe = &entry.recipe;
+                recipe.yields.iter().chain(
+                    recipe
+                        .all_ingredients()
+                        .filter_map(|ingredient| ingredient.amount.as_ref()),
+                )
+            })
+            .filter_map(|amount| amount.unit.as_deref())
+            .collect()
+    }
+
+    /// Returns the collection with the longest root that contains the path.
+    fn collection_index(&self, path: &Path) -> Option<usize> {
+        self.collections
+            .iter()
+            .enumerate()
+            .filter(|(_, collection)| path.starts_with(collection.root()))
+            .max_by_key(|(_, collection)| collection.root().components().count())
+            .map(|(i, _)| i)
+    }
+}
+
+pub fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    let rest = uri.as_str().strip_prefix("file://")?;
+    // skip the authority, e.g. `localhost`
+    let path = percent_decode(&rest[rest.find('/')?..]);
+    // `/C:/Users` on Windows
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &path[1..],
+        _ => &path,
+    };
+    Some(PathBuf::from(path))
+}
+
+pub fn path_to_uri(path: &Path) -> Option<Uri> {
+    let path = path.to_str()?.replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{b:02X}")),
+        }
+    }
+    uri.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn convert_uris() {
+        let uri: Uri = "file:///home/me/Rezepte/K%C3%A4se%20Sp%C3%A4tzle.md"
+            .parse()
+            .unwrap();
+        let path = uri_to_path(&uri).unwrap();
+
+        assert_eq!(path, Path::new("/home/me/Rezepte/Käse Spätzle.md"));
+        assert_eq!(path_to_uri(&path), Some(uri));
+        assert_eq!(uri_to_path(&"untitled:Untitled-1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn incremental_changes() {
+        let uri: Uri = "untitled:Untitled-1".parse().unwrap();
+        let mut workspace = Workspace::default();
+        workspace.update(uri.clone(), "# Tea\n\n---\n\n- *1* cup\n".to_owned());
+
+        let range = lsp_types::Range::new(
+            lsp_types::Position::new(4, 3),
+            lsp_types::Position::new(4, 4),
+        );
+        workspace.edit(&uri, range, "2");
+
+        let document = workspace.document(&uri).unwrap();
+        assert_eq!(document.src(), "# Tea\n\n---\n\n- *2* cup\n");
+        let recipe = document.inner.recipe().unwrap();
+        assert_eq!(
+            recipe.ingredients[0].amount.as_ref().unwrap().to_string(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn typing_empty_headings() {
+        let uri: Uri = "untitled:Untitled-1".parse().unwrap();
+        let mut workspace = Workspace::default();
+        workspace.update(uri.clone(), "#".to_owned());
+        workspace.update(uri.clone(), "# Tea\n\n---\n\n- *1* cup\n".to_owned());
+
+        for (line, character, text) in [(5, 0, "\n"), (6, 0, "#"), (6, 1, "#"), (6, 2, " ")] {
+            let position = lsp_types::Position::new(line, character);
+            workspace.edit(&uri, lsp_types::Range::new(position, position), text);
+        }
+
+        let document = workspace.document(&uri).unwrap();
+        assert_eq!(document.src(), "# Tea\n\n---\n\n- *1* cup\n\n## ");
+        let recipe = document.inner.recipe().unwrap();
+        assert_eq!(recipe.ingredient_groups[0].title, "");
+    }
+}
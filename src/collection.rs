@@ -0,0 +1,372 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Load all recipes from a directory tree.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::{Error, Ingredient, ParseOptions, Recipe};
+
+/// A collection of recipes that were loaded from a directory.
+///
+/// Files that can't be read or parsed don't prevent loading the rest of the collection,
+/// instead, their errors are available from [`Collection::errors`].
+///
+/// ```no_run
+/// # use recipemd::collection::Collection;
+/// # fn main() -> std::io::Result<()> {
+/// let collection = Collection::load("recipes")?;
+///
+/// for entry in collection.by_tag("vegan") {
+///     println!("{}: {}", entry.path.display(), entry.recipe.title);
+/// }
+/// for error in collection.errors() {
+///     eprintln!("{error}");
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Default)]
+pub struct Collection {
+    root: PathBuf,
+    entries: Vec<Entry>,
+    errors: Vec<LoadError>,
+    options: ParseOptions,
+}
+
+/// A recipe and the path of the file it was loaded from.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// The path of the file relative to the root of the collection.
+    pub path: PathBuf,
+    pub recipe: Recipe,
+}
+
+/// Returned if a file of a [`Collection`] could not be loaded.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    #[error("failed to read {}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to parse {}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: Error,
+    },
+}
+
+impl LoadError {
+    /// Returns the path of the file that could not be loaded, relative to the root of the
+    /// collection.
+    pub fn path(&self) -> &Path {
+        match self {
+            LoadError::Io { path, .. } | LoadError::Parse { path, .. } => path,
+        }
+    }
+}
+
+impl Collection {
+    /// Recursively loads all `*.md` files in a directory, skipping hidden files and directories.
+    ///
+    /// Returns an error only if the directory itself can't be read.
+    pub fn load(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        Self::load_with_options(root, &ParseOptions::default())
+    }
+
+    /// Recursively loads all `*.md` files in a directory like [`Collection::load`], parsing
+    /// them with the given options.
+    pub fn load_with_options(
+        root: impl AsRef<Path>,
+        options: &ParseOptions,
+    ) -> std::io::Result<Self> {
+        let root = root.as_ref().to_owned();
+        let mut collection = Collection {
+            root,
+            ..Default::default()
+        }
+        .with_options(options.clone());
+
+        let mut files = Vec::new();
+        let mut dirs = vec![collection.root.clone()];
+        let mut is_root = true;
+
+        while let Some(dir) = dirs.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if is_root => return Err(e),
+                Err(source) => {
+                    collection.errors.push(LoadError::Io {
+                        path: collection.relative_path(&dir),
+                        source,
+                    });
+                    continue;
+                }
+            };
+            is_root = false;
+
+            for entry in entries {
+                let (entry, file_type) = match entry.and_then(|e| Ok((e.file_type()?, e))) {
+                    Ok((file_type, entry)) => (entry, file_type),
+                    Err(source) => {
+                        collection.errors.push(LoadError::Io {
+                            path: collection.relative_path(&dir),
+                            source,
+                        });
+                        continue;
+                    }
+                };
+                let path = entry.path();
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                if file_type.is_dir() {
+                    dirs.push(path);
+                } else if path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+                {
+                    files.push(path);
+                }
+            }
+        }
+
+        files.sort();
+        for file in files {
+            let path = collection.relative_path(&file);
+            match std::fs::read_to_string(&file) {
+                Ok(src) => collection.insert(path, &src),
+                Err(source) => collection.errors.push(LoadError::Io { path, source }),
+            }
+        }
+
+        Ok(collection)
+    }
+
+    /// Sets the options that are used to parse recipes added by [`Collection::insert`].
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parses a recipe and adds it to the collection, replacing any recipe with the same path.
+    ///
+    /// If the recipe can't be parsed, the error is recorded instead.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, src: &str) {
+        let path = path.into();
+        self.remove(&path);
+
+        match Recipe::parse_with_options(src, &self.options) {
+            Ok(recipe) => self.insert_recipe(path, recipe),
+            Err(source) => self.errors.push(LoadError::Parse { path, source }),
+        }
+    }
+
+    /// Adds a recipe that was already parsed, replacing any recipe with the same path.
+    pub fn insert_recipe(&mut self, path: impl Into<PathBuf>, recipe: Recipe) {
+        let path = path.into();
+        self.remove(&path);
+
+        let i = self.entries.partition_point(|entry| entry.path < path);
+        self.entries.insert(i, Entry { path, recipe });
+    }
+
+    /// Removes the recipe or error with the given path from the collection.
+    pub fn remove(&mut self, path: impl AsRef<Path>) -> Option<Entry> {
+        let path = self.relative_path(path.as_ref());
+        self.errors.retain(|error| error.path() != path);
+        let i = self.entries.iter().position(|entry| entry.path == path)?;
+        Some(self.entries.remove(i))
+    }
+
+    /// Returns the directory the collection was loaded from.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns all recipes that were loaded successfully, ordered by their path.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the errors of all files that could not be loaded.
+    pub fn errors(&self) -> &[LoadError] {
+        &self.errors
+    }
+
+    /// Returns the recipe with the given path, which may be absolute or relative to the root of
+    /// the collection.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&Entry> {
+        let path = self.relative_path(path.as_ref());
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Returns all recipes with the given title, ignoring case.
+    pub fn by_title(&self, title: &str) -> impl Iterator<Item = &Entry> {
+        let title = title.to_lowercase();
+        self.entries
+            .iter()
+            .filter(move |entry| entry.recipe.title.to_lowercase() == title)
+    }
+
+    /// Returns all recipes with the given tag, ignoring case.
+    pub fn by_tag(&self, tag: &str) -> impl Iterator<Item = &Entry> {
+        let tag = tag.to_lowercase();
+        self.entries.iter().filter(move |entry| {
+            entry
+                .recipe
+                .tags
+                .iter()
+                .any(|other| other.to_lowercase() == tag)
+        })
+    }
+
+    /// Resolves a link from the recipe at path `from` to another recipe of the collection.
+    ///
+    /// Links are interpreted relative to the directory of `from`. Links with a URL scheme like
+    /// `https://` and links that point outside of the collection can't be resolved.
+    pub fn resolve_link(&self, from: impl AsRef<Path>, link: &str) -> Option<&Entry> {
+        let link = link.split(['#', '?']).next().unwrap_or_default();
+        if link.is_empty() || link.contains("://") || link.starts_with("mailto:") {
+            return None;
+        }
+        let link = percent_decode(link);
+
+        let from = self.relative_path(from.as_ref());
+        let mut path = match link.starts_with('/') {
+            true => PathBuf::new(),
+            false => from.parent().map(ToOwned::to_owned).unwrap_or_default(),
+        };
+        for component in Path::new(link.trim_start_matches('/')).components() {
+            match component {
+                Component::ParentDir if !path.pop() => return None,
+                Component::Normal(c) => path.push(c),
+                _ => (),
+            }
+        }
+
+        self.get(path)
+    }
+
+    /// Resolves the link of an ingredient of the recipe at path `from`.
+    ///
+    /// See [`Collection::resolve_link`] for details.
+    pub fn resolve_ingredient(
+        &self,
+        from: impl AsRef<Path>,
+        ingredient: &Ingredient,
+    ) -> Option<&Entry> {
+        self.resolve_link(from, ingredient.link.as_deref()?)
+    }
+
+    fn relative_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).to_owned()
+    }
+}
+
+impl<'a> IntoIterator for &'a Collection {
+    type Item = &'a Entry;
+    type IntoIter = std::slice::Iter<'a, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// Decodes percent-encoded bytes in a link or URI path, e.g. `Pizza%20Dough.md`.
+///
+/// Invalid escapes are kept as they are and invalid UTF-8 is replaced.
+///
+/// This is only public so that the `recipemd-lsp` binary can resolve links like
+/// [`Collection::resolve_link`] does. It is not part of the stable API.
+///
+/// ```
+/// # use recipemd::collection::percent_decode;
+/// assert_eq!(percent_decode("Pizza%20Dough.md"), "Pizza Dough.md");
+/// assert_eq!(percent_decode("100%.md"), "100%.md");
+/// ```
+#[doc(hidden)]
+pub fn percent_decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = iter.clone().take(2).collect::<Vec<u8>>();
+            if let Some(decoded) = std::str::from_utf8(&hex)
+                .ok()
+                .filter(|hex| hex.len() == 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(decoded);
+                iter.nth(1);
+                continue;
+            }
+        }
+        bytes.push(b);
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn load_collection() {
+        let root = std::env::temp_dir().join(format!("recipemd-collection-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("basics/.hidden")).unwrap();
+        std::fs::write(
+            root.join("pizza.md"),
+            "# Pizza\n\n*vegan*\n\n---\n\n- *1* [dough](basics/pizza%20dough.md)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("basics/pizza dough.md"),
+            "# Pizza Dough\n\n*Vegan, basic*\n\n---\n\n- flour\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("basics/broken.md"), "no title").unwrap();
+        std::fs::write(root.join("basics/.hidden/hidden.md"), "# Hidden\n\n---\n").unwrap();
+        std::fs::write(root.join("notes.txt"), "not a recipe").unwrap();
+
+        let collection = Collection::load(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let paths: Vec<&Path> = collection
+            .entries()
+            .iter()
+            .map(|e| e.path.as_path())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![Path::new("basics/pizza dough.md"), Path::new("pizza.md")]
+        );
+        assert_eq!(collection.errors().len(), 1);
+        assert_eq!(collection.errors()[0].path(), Path::new("basics/broken.md"));
+
+        assert_eq!(collection.by_tag("VEGAN").count(), 2);
+        assert_eq!(collection.by_title("pizza").count(), 1);
+
+        let pizza = collection.get(root.join("pizza.md")).unwrap();
+        let dough = collection
+            .resolve_ingredient(&pizza.path, &pizza.recipe.ingredients[0])
+            .unwrap();
+        assert_eq!(dough.recipe.title, "Pizza Dough");
+        assert_eq!(
+            collection
+                .resolve_link("basics/pizza dough.md", "../pizza.md#top")
+                .map(|e| e.recipe.title.as_str()),
+            Some("Pizza")
+        );
+        assert!(collection
+            .resolve_link("pizza.md", "../outside.md")
+            .is_none());
+    }
+}
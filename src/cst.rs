@@ -0,0 +1,516 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! A lossless concrete syntax tree of a recipe, for tools that edit recipes in place.
+//!
+//! Unlike [`Recipe`](crate::Recipe), the tree keeps every byte of the source, including
+//! whitespace, markup and text that is not part of the recipe. The tokens of the tree
+//! concatenate to exactly the source, so changing one part of it leaves all other bytes
+//! unchanged.
+//!
+//! ```
+//! # use recipemd::cst::{SyntaxKind, SyntaxTree};
+//! let src = "# Cake\n\n*sweet, baked*\n\n---\n\n- *200 g* flour\n\n## Topping\n\n- *100 ml*  cream\n";
+//! let mut tree = SyntaxTree::parse(src);
+//!
+//! // change one amount
+//! let amount = tree.root().find(SyntaxKind::Amount).unwrap().span.clone();
+//! tree.replace(amount, "250 g");
+//!
+//! // add an ingredient to a group
+//! let group = tree
+//!     .root()
+//!     .descendants()
+//!     .find(|node| node.kind == SyntaxKind::IngredientGroup)
+//!     .unwrap();
+//! let last = group.nodes().filter(|node| node.kind == SyntaxKind::Ingredient).last().unwrap();
+//! tree.insert(last.span.end, "\n- *3* cherries");
+//!
+//! // rename a tag
+//! let tag = tree
+//!     .root()
+//!     .descendants()
+//!     .find(|node| node.kind == SyntaxKind::Tag && tree.text(node) == "sweet")
+//!     .unwrap()
+//!     .span
+//!     .clone();
+//! tree.replace(tag, "dessert");
+//!
+//! assert_eq!(
+//!     tree.src(),
+//!     "# Cake\n\n*dessert, baked*\n\n---\n\n- *250 g* flour\n\n## Topping\n\n- *100 ml*  cream\n- *3* cherries\n"
+//! );
+//! ```
+
+use std::ops::Range;
+
+use lazy_regex::regex;
+
+use crate::document::{RecipeDocument, SourceMap};
+
+/// The concrete syntax tree of a recipe together with its source.
+#[derive(Debug)]
+pub struct SyntaxTree {
+    document: RecipeDocument,
+    root: SyntaxNode,
+}
+
+/// A node of a [`SyntaxTree`], which covers a part of the source that has a meaning in the
+/// recipe.
+///
+/// The children of a node cover its whole span without gaps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub span: Range<usize>,
+    pub children: Vec<SyntaxElement>,
+}
+
+/// A child of a [`SyntaxNode`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A leaf of a [`SyntaxTree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// The kind of a [`SyntaxNode`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// The whole source.
+    Recipe,
+    /// The front matter block, including its delimiters.
+    FrontMatter,
+    /// The title or the heading of an ingredient group.
+    Heading,
+    /// The text of a [`Heading`](SyntaxKind::Heading).
+    HeadingText,
+    Description,
+    /// The emphasized paragraph that contains the [tags](SyntaxKind::Tag).
+    Tags,
+    Tag,
+    /// The strong paragraph that contains the [yields](SyntaxKind::Yield).
+    Yields,
+    Yield,
+    /// A horizontal line before or after the ingredients.
+    Divider,
+    /// The heading, ingredients and nested groups of an ingredient group.
+    IngredientGroup,
+    /// The list item of an ingredient, including nested lists.
+    Ingredient,
+    /// The text of an amount, without the surrounding `*`.
+    Amount,
+    /// Everything after the amount of an ingredient.
+    Name,
+    /// The link of an ingredient, including its text and destination.
+    Link,
+    Instructions,
+}
+
+/// The kind of a [`SyntaxToken`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Spaces, tabs and line breaks between other tokens.
+    Whitespace,
+    /// Markup like `#`, `*` or `---` and text that is not part of the recipe.
+    Syntax,
+    /// The content of a node, e.g. the text of a tag.
+    Text,
+}
+
+impl SyntaxKind {
+    /// Returns `true` if the text of the node is content rather than structure.
+    fn is_content(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::FrontMatter
+                | SyntaxKind::HeadingText
+                | SyntaxKind::Description
+                | SyntaxKind::Tag
+                | SyntaxKind::Yield
+                | SyntaxKind::Amount
+                | SyntaxKind::Name
+                | SyntaxKind::Link
+                | SyntaxKind::Instructions
+        )
+    }
+}
+
+impl SyntaxTree {
+    /// Parses a recipe into a syntax tree.
+    ///
+    /// Like [`RecipeDocument::parse`], this never fails. Parts of an invalid recipe that could
+    /// not be parsed are kept as [`TokenKind::Syntax`] tokens.
+    pub fn parse(src: impl Into<String>) -> Self {
+        let document = RecipeDocument::parse(src);
+        let root = build(document.src(), document.source_map());
+        Self { document, root }
+    }
+
+    /// Returns the markdown source of the recipe.
+    pub fn src(&self) -> &str {
+        self.document.src()
+    }
+
+    /// Returns the node that covers the whole source.
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// Returns the parsed recipe and its source map.
+    pub fn document(&self) -> &RecipeDocument {
+        &self.document
+    }
+
+    /// Returns the source text of a node.
+    pub fn text(&self, node: &SyntaxNode) -> &str {
+        &self.src()[node.span.clone()]
+    }
+
+    /// Replaces a byte range of the source and parses the result.
+    ///
+    /// All spans of the tree are updated, so nodes must be looked up again after an edit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or doesn't lie on character boundaries.
+    pub fn replace(&mut self, span: Range<usize>, text: &str) {
+        let mut src = self.src().to_owned();
+        src.replace_range(span, text);
+        *self = Self::parse(src);
+    }
+
+    /// Inserts text at a byte offset of the source and parses the result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset is out of bounds or not on a character boundary.
+    pub fn insert(&mut self, offset: usize, text: &str) {
+        self.replace(offset..offset, text)
+    }
+}
+
+impl std::fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.src())
+    }
+}
+
+impl SyntaxNode {
+    /// Returns the direct children that are nodes.
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Returns all nodes below this one, in the order of the source.
+    pub fn descendants(&self) -> Box<dyn Iterator<Item = &SyntaxNode> + '_> {
+        Box::new(
+            self.nodes()
+                .flat_map(|node| std::iter::once(node).chain(node.descendants())),
+        )
+    }
+
+    /// Returns all tokens below this node, in the order of the source.
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &SyntaxToken> + '_> {
+        Box::new(self.children.iter().flat_map(|child| match child {
+            SyntaxElement::Node(node) => node.tokens(),
+            SyntaxElement::Token(token) => Box::new(std::iter::once(token)),
+        }))
+    }
+
+    /// Returns the first node of the given kind below this one.
+    pub fn find(&self, kind: SyntaxKind) -> Option<&SyntaxNode> {
+        self.descendants().find(|node| node.kind == kind)
+    }
+}
+
+/// Builds the tree from the spans of a source map.
+fn build(src: &str, map: &SourceMap) -> SyntaxNode {
+    let mut spans = Vec::new();
+
+    spans.extend(
+        map.front_matter
+            .clone()
+            .map(|s| (SyntaxKind::FrontMatter, s)),
+    );
+    spans.extend(map.title_heading.clone().map(|s| (SyntaxKind::Heading, s)));
+    spans.extend(map.title.clone().map(|s| (SyntaxKind::HeadingText, s)));
+    spans.extend(
+        map.description
+            .clone()
+            .map(|s| (SyntaxKind::Description, s)),
+    );
+    spans.extend(map.tags_block.clone().map(|s| (SyntaxKind::Tags, s)));
+    spans.extend(map.tags.iter().map(|s| (SyntaxKind::Tag, s.clone())));
+    spans.extend(map.yields_block.clone().map(|s| (SyntaxKind::Yields, s)));
+    spans.extend(map.yields.iter().map(|s| (SyntaxKind::Yield, s.clone())));
+    spans.extend(
+        map.dividers
+            .iter()
+            .map(|s| (SyntaxKind::Divider, s.clone())),
+    );
+    for group in &map.ingredient_groups {
+        spans.push((SyntaxKind::IngredientGroup, group.span.clone()));
+        spans.push((SyntaxKind::Heading, group.heading.clone()));
+        spans.push((SyntaxKind::HeadingText, group.title.clone()));
+    }
+    for ingredient in &map.ingredients {
+        spans.push((SyntaxKind::Ingredient, ingredient.span.clone()));
+        let name_start = match &ingredient.amount {
+            Some(amount) => {
+                spans.push((SyntaxKind::Amount, amount.clone()));
+                // skip the closing `*` or `_` of the amount
+                amount.end + 1
+            }
+            None => {
+                let item = &src[ingredient.span.clone()];
+                let marker = regex!(r"^(?:[-*+]|\d+[.)])").find(item);
+                ingredient.span.start + marker.map_or(0, |m| m.end())
+            }
+        };
+        let name = trim(src, name_start..ingredient.span.end);
+        if !name.is_empty() {
+            spans.push((SyntaxKind::Name, name));
+        }
+        spans.extend(ingredient.link.clone().map(|s| (SyntaxKind::Link, s)));
+    }
+    spans.extend(
+        map.instructions
+            .clone()
+            .map(|s| (SyntaxKind::Instructions, s)),
+    );
+
+    // parents were added before their children, so a stable sort keeps them in front
+    spans.sort_by_key(|(_, span)| span.start);
+
+    let mut nodes = vec![Pending {
+        kind: SyntaxKind::Recipe,
+        span: 0..src.len(),
+        children: Vec::new(),
+    }];
+    let mut stack = vec![0];
+    for (kind, span) in spans {
+        while let Some(&parent) = stack.last() {
+            let parent_span = &nodes[parent].span;
+            if parent_span.start <= span.start && span.end <= parent_span.end {
+                break;
+            }
+            stack.pop();
+        }
+        let parent = *stack.last().expect("the root contains every span");
+        nodes.push(Pending {
+            kind,
+            span,
+            children: Vec::new(),
+        });
+        let index = nodes.len() - 1;
+        nodes[parent].children.push(index);
+        stack.push(index);
+    }
+
+    finish(src, &nodes, 0)
+}
+
+/// A node whose children are indices into the list of all nodes.
+struct Pending {
+    kind: SyntaxKind,
+    span: Range<usize>,
+    children: Vec<usize>,
+}
+
+/// Converts a pending node and fills the gaps between its children with tokens.
+fn finish(src: &str, nodes: &[Pending], index: usize) -> SyntaxNode {
+    let pending = &nodes[index];
+    let mut children = Vec::new();
+    let mut pos = pending.span.start;
+
+    for &child in &pending.children {
+        let child = finish(src, nodes, child);
+        tokens(src, pending.kind, pos..child.span.start, &mut children);
+        pos = child.span.end;
+        children.push(SyntaxElement::Node(child));
+    }
+    tokens(src, pending.kind, pos..pending.span.end, &mut children);
+
+    SyntaxNode {
+        kind: pending.kind,
+        span: pending.span.clone(),
+        children,
+    }
+}
+
+/// Adds the tokens of a gap between the children of a node.
+fn tokens(src: &str, parent: SyntaxKind, span: Range<usize>, out: &mut Vec<SyntaxElement>) {
+    if span.is_empty() {
+        return;
+    }
+    if parent.is_content() {
+        out.push(SyntaxElement::Token(SyntaxToken {
+            kind: TokenKind::Text,
+            span,
+        }));
+        return;
+    }
+
+    // split the gap into runs of whitespace and markup
+    let mut start = span.start;
+    let mut kind = None;
+    for (i, c) in src[span.clone()].char_indices() {
+        let i = span.start + i;
+        let next = token_kind(c);
+        if let Some(kind) = kind.filter(|&kind| kind != next) {
+            out.push(SyntaxElement::Token(SyntaxToken {
+                kind,
+                span: start..i,
+            }));
+            start = i;
+        }
+        kind = Some(next);
+    }
+    if let Some(kind) = kind {
+        out.push(SyntaxElement::Token(SyntaxToken {
+            kind,
+            span: start..span.end,
+        }));
+    }
+}
+
+fn token_kind(c: char) -> TokenKind {
+    match c.is_whitespace() {
+        true => TokenKind::Whitespace,
+        false => TokenKind::Syntax,
+    }
+}
+
+/// Shrinks a span so that it doesn't include leading or trailing whitespace.
+fn trim(src: &str, span: Range<usize>) -> Range<usize> {
+    let s = &src[span.clone()];
+    let start = span.start + (s.len() - s.trim_start().len());
+    let end = span.end - (s.len() - s.trim_end().len());
+    start..end.max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn concat(tree: &SyntaxTree) -> String {
+        tree.root()
+            .tokens()
+            .map(|token| &tree.src()[token.span.clone()])
+            .collect()
+    }
+
+    /// Returns the depth, kind and text of all nodes.
+    fn outline(tree: &SyntaxTree) -> Vec<(usize, SyntaxKind, &str)> {
+        fn walk<'t>(
+            tree: &'t SyntaxTree,
+            node: &'t SyntaxNode,
+            depth: usize,
+            out: &mut Vec<(usize, SyntaxKind, &'t str)>,
+        ) {
+            for child in node.nodes() {
+                out.push((depth, child.kind, tree.text(child)));
+                walk(tree, child, depth + 1, out);
+            }
+        }
+        let mut out = Vec::new();
+        walk(tree, tree.root(), 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn lossless() {
+        for src in [
+            "---\nsource: grandma\n---\n\nCake\n====\nA *simple* cake.\n\n**1 cake,  12 pieces**\n\n*sweet ,, baked*\n\n[ref]: https://example.org\n\n- - -\n* *200 g*flour\n1. [butter](butter.md)\n   - or margarine\n\nTopping ##\n-------\n### Cream\n+ *200 ml* cream\n\n___\n\nBake it.\n\n\n",
+            "# Cake\n\n---\n\n- *200 g* flour\n- *1*\n\nrest of the file",
+            "no recipe at all\r\n",
+            "",
+        ] {
+            let tree = SyntaxTree::parse(src);
+            assert_eq!(concat(&tree), src);
+            assert_eq!(tree.root().span, 0..src.len());
+        }
+    }
+
+    #[test]
+    fn structure() {
+        let src = "# Cake\n\n*sweet, baked*\n\n**1 cake**\n\n---\n\n- *200 g* flour\n\n## Topping\n\n- [cream](cream.md)\n\n---\n\nBake it.\n";
+        let tree = SyntaxTree::parse(src);
+
+        use SyntaxKind::*;
+        assert_eq!(
+            outline(&tree),
+            [
+                (0, Heading, "# Cake"),
+                (1, HeadingText, "Cake"),
+                (0, Tags, "*sweet, baked*"),
+                (1, Tag, "sweet"),
+                (1, Tag, "baked"),
+                (0, Yields, "**1 cake**"),
+                (1, Yield, "1 cake"),
+                (0, Divider, "---"),
+                (0, Ingredient, "- *200 g* flour"),
+                (1, Amount, "200 g"),
+                (1, Name, "flour"),
+                (0, IngredientGroup, "## Topping\n\n- [cream](cream.md)"),
+                (1, Heading, "## Topping"),
+                (2, HeadingText, "Topping"),
+                (1, Ingredient, "- [cream](cream.md)"),
+                (2, Name, "[cream](cream.md)"),
+                (3, Link, "[cream](cream.md)"),
+                (0, Divider, "---"),
+                (0, Instructions, "Bake it."),
+            ]
+        );
+
+        let ingredient = tree.root().find(SyntaxKind::Ingredient).unwrap();
+        assert_eq!(
+            ingredient
+                .tokens()
+                .map(|token| (token.kind, &src[token.span.clone()]))
+                .collect::<Vec<_>>(),
+            [
+                (TokenKind::Syntax, "-"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Syntax, "*"),
+                (TokenKind::Text, "200 g"),
+                (TokenKind::Syntax, "*"),
+                (TokenKind::Whitespace, " "),
+                (TokenKind::Text, "flour"),
+            ]
+        );
+    }
+
+    #[test]
+    fn edits_keep_other_bytes() {
+        let src = "# Cake\n\n*sweet,   baked*\n\n---\n\n* *200 g*   flour\n\n## Topping\n\n1. cream\n\n---\n\nBake it.";
+        let mut tree = SyntaxTree::parse(src);
+
+        let tag = tree.root().find(SyntaxKind::Tag).unwrap().span.clone();
+        tree.replace(tag, "dessert");
+        let name = tree.root().find(SyntaxKind::Name).unwrap().span.clone();
+        tree.replace(name, "spelt flour");
+        let group = tree.root().find(SyntaxKind::IngredientGroup).unwrap();
+        tree.insert(group.span.end, "\n2. sugar");
+
+        assert_eq!(
+            tree.to_string(),
+            "# Cake\n\n*dessert,   baked*\n\n---\n\n* *200 g*   spelt flour\n\n## Topping\n\n1. cream\n2. sugar\n\n---\n\nBake it."
+        );
+        let recipe = tree.document().recipe().unwrap();
+        assert_eq!(recipe.tags, ["dessert", "baked"]);
+        assert_eq!(recipe.ingredient_groups[0].ingredients[1].name, "sugar");
+    }
+}
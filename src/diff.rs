@@ -0,0 +1,491 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Compare two versions of a recipe.
+
+use crate::{Amount, Ingredient, IngredientGroup, Recipe};
+
+/// The changes between two versions of a recipe, see [`diff`].
+///
+/// The [`Display`](std::fmt::Display) implementation writes one change per line, e.g.
+///
+/// ```text
+/// title: Cake -> Lemon cake
+/// + tag: vegan
+/// ~ ingredient: flour: *200 g* -> *250 g*
+/// instructions:
+///   Mix everything.
+/// - Bake it.
+/// + Bake it for 30 minutes.
+/// ```
+#[derive(Clone, Debug)]
+pub struct RecipeDiff {
+    pub changes: Vec<Change>,
+}
+
+impl RecipeDiff {
+    /// Returns `true` if both recipes are the same.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// A single change between two versions of a recipe.
+#[derive(Clone, Debug)]
+pub enum Change {
+    Title {
+        old: String,
+        new: String,
+    },
+    Description {
+        old: Option<String>,
+        new: Option<String>,
+    },
+    TagAdded(String),
+    TagRemoved(String),
+    Yields {
+        old: Vec<Amount>,
+        new: Vec<Amount>,
+    },
+    /// An ingredient that only exists in the new recipe, with the titles of its ingredient
+    /// groups.
+    IngredientAdded {
+        group: Vec<String>,
+        ingredient: Ingredient,
+    },
+    /// An ingredient that only exists in the old recipe, with the titles of its ingredient
+    /// groups.
+    IngredientRemoved {
+        group: Vec<String>,
+        ingredient: Ingredient,
+    },
+    /// An ingredient whose amount changed or that was moved to another ingredient group.
+    IngredientChanged {
+        name: String,
+        old_group: Vec<String>,
+        new_group: Vec<String>,
+        old_amount: Option<Amount>,
+        new_amount: Option<Amount>,
+    },
+    /// The lines of the old and new instructions.
+    Instructions(Vec<DiffLine>),
+}
+
+/// A line of a text diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Compares two versions of a recipe.
+///
+/// Ingredients are matched by their name (ignoring case) across all ingredient groups, so
+/// moving an ingredient to another group or changing its amount or unit is reported as a
+/// change instead of a removal and an addition. Descriptions and instructions are compared
+/// line by line.
+///
+/// ```
+/// # use recipemd::{diff::{diff, Change}, Recipe};
+/// # fn main() -> recipemd::Result<()> {
+/// let old = Recipe::parse("# Tea\n\n*hot*\n\n---\n\n- *1* tea bag\n- *250 ml* water\n")?;
+/// let new = Recipe::parse("# Tea\n\n*hot, vegan*\n\n---\n\n- *2* tea bags\n- *0.3 l* Water\n")?;
+/// let diff = diff(&old, &new);
+///
+/// assert!(matches!(&diff.changes[0], Change::TagAdded(tag) if tag == "vegan"));
+/// assert_eq!(
+///     diff.to_string(),
+///     "+ tag: vegan\n\
+///      - ingredient: *1* tea bag\n\
+///      ~ ingredient: Water: *250 ml* -> *0.3 l*\n\
+///      + ingredient: *2* tea bags\n"
+/// );
+/// # Ok(()) }
+/// ```
+pub fn diff(old: &Recipe, new: &Recipe) -> RecipeDiff {
+    let mut changes = Vec::new();
+
+    if old.title != new.title {
+        changes.push(Change::Title {
+            old: old.title.clone(),
+            new: new.title.clone(),
+        });
+    }
+    if old.description != new.description {
+        changes.push(Change::Description {
+            old: old.description.clone(),
+            new: new.description.clone(),
+        });
+    }
+    for tag in &new.tags {
+        if !old.tags.contains(tag) {
+            changes.push(Change::TagAdded(tag.clone()));
+        }
+    }
+    for tag in &old.tags {
+        if !new.tags.contains(tag) {
+            changes.push(Change::TagRemoved(tag.clone()));
+        }
+    }
+    if old.yields.len() != new.yields.len()
+        || old
+            .yields
+            .iter()
+            .zip(&new.yields)
+            .any(|(a, b)| !same_amount(a, b))
+    {
+        changes.push(Change::Yields {
+            old: old.yields.clone(),
+            new: new.yields.clone(),
+        });
+    }
+
+    diff_ingredients(old, new, &mut changes);
+
+    if old.instructions != new.instructions {
+        changes.push(Change::Instructions(diff_lines(
+            old.instructions.as_deref().unwrap_or_default(),
+            new.instructions.as_deref().unwrap_or_default(),
+        )));
+    }
+
+    RecipeDiff { changes }
+}
+
+fn diff_ingredients(old: &Recipe, new: &Recipe, changes: &mut Vec<Change>) {
+    let old = grouped_ingredients(old);
+    let mut new: Vec<_> = grouped_ingredients(new).into_iter().map(Some).collect();
+
+    for (old_group, old_ingredient) in old {
+        let key = old_ingredient.name.trim().to_lowercase();
+        let matching = new.iter_mut().find(|entry| {
+            entry
+                .as_ref()
+                .is_some_and(|(_, ingredient)| ingredient.name.trim().to_lowercase() == key)
+        });
+        let Some((new_group, new_ingredient)) = matching.and_then(Option::take) else {
+            changes.push(Change::IngredientRemoved {
+                group: old_group,
+                ingredient: old_ingredient.clone(),
+            });
+            continue;
+        };
+        if !same_optional_amount(&old_ingredient.amount, &new_ingredient.amount)
+            || old_group != new_group
+        {
+            changes.push(Change::IngredientChanged {
+                name: new_ingredient.name.clone(),
+                old_group,
+                new_group,
+                old_amount: old_ingredient.amount.clone(),
+                new_amount: new_ingredient.amount.clone(),
+            });
+        }
+    }
+    for (group, ingredient) in new.into_iter().flatten() {
+        changes.push(Change::IngredientAdded {
+            group,
+            ingredient: ingredient.clone(),
+        });
+    }
+}
+
+/// Returns all ingredients with the titles of their groups.
+fn grouped_ingredients(recipe: &Recipe) -> Vec<(Vec<String>, &Ingredient)> {
+    fn walk<'a>(
+        groups: &'a [IngredientGroup],
+        path: &mut Vec<String>,
+        out: &mut Vec<(Vec<String>, &'a Ingredient)>,
+    ) {
+        for group in groups {
+            path.push(group.title.clone());
+            out.extend(group.ingredients.iter().map(|i| (path.clone(), i)));
+            walk(&group.ingredient_groups, path, out);
+            path.pop();
+        }
+    }
+
+    let mut out: Vec<_> = recipe.ingredients.iter().map(|i| (Vec::new(), i)).collect();
+    walk(&recipe.ingredient_groups, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Compares the value and unit of two amounts, so that e.g. `1/2` and `0.5` are the same.
+fn same_amount(a: &Amount, b: &Amount) -> bool {
+    a.unit == b.unit && f32::from(a.factor) == f32::from(b.factor)
+}
+
+fn same_optional_amount(a: &Option<Amount>, b: &Option<Amount>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => same_amount(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Returns a line based diff of two texts, using their longest common subsequence.
+fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Unchanged(old[i].to_owned()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Removed(old[i].to_owned()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j].to_owned()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+impl std::fmt::Display for RecipeDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in &self.changes {
+            match change {
+                Change::Title { old, new } => writeln!(f, "title: {old} -> {new}")?,
+                Change::Description { old, new } => {
+                    writeln!(f, "description:")?;
+                    let lines = diff_lines(
+                        old.as_deref().unwrap_or_default(),
+                        new.as_deref().unwrap_or_default(),
+                    );
+                    write_lines(f, &lines)?;
+                }
+                Change::TagAdded(tag) => writeln!(f, "+ tag: {tag}")?,
+                Change::TagRemoved(tag) => writeln!(f, "- tag: {tag}")?,
+                Change::Yields { old, new } => {
+                    let join = |yields: &[Amount]| {
+                        yields
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    writeln!(f, "yields: {} -> {}", join(old), join(new))?
+                }
+                Change::IngredientAdded { group, ingredient } => {
+                    write!(f, "+ ingredient: ")?;
+                    write_ingredient(f, group, ingredient)?;
+                }
+                Change::IngredientRemoved { group, ingredient } => {
+                    write!(f, "- ingredient: ")?;
+                    write_ingredient(f, group, ingredient)?;
+                }
+                Change::IngredientChanged {
+                    name,
+                    old_group,
+                    new_group,
+                    old_amount,
+                    new_amount,
+                } => {
+                    write!(f, "~ ingredient: {name}:")?;
+                    let amount = |amount: &Option<Amount>| match amount {
+                        Some(amount) => format!("*{amount}*"),
+                        None => "no amount".to_owned(),
+                    };
+                    if old_group == new_group {
+                        writeln!(f, " {} -> {}", amount(old_amount), amount(new_amount))?;
+                    } else {
+                        let group = |group: &[String]| match group.is_empty() {
+                            true => "top level".to_owned(),
+                            false => group.join(" > "),
+                        };
+                        write!(
+                            f,
+                            " moved from {} to {}",
+                            group(old_group),
+                            group(new_group)
+                        )?;
+                        match same_optional_amount(old_amount, new_amount) {
+                            false => {
+                                writeln!(f, ", {} -> {}", amount(old_amount), amount(new_amount))?
+                            }
+                            true => writeln!(f)?,
+                        }
+                    }
+                }
+                Change::Instructions(lines) => {
+                    writeln!(f, "instructions:")?;
+                    write_lines(f, lines)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_ingredient(
+    f: &mut std::fmt::Formatter<'_>,
+    group: &[String],
+    ingredient: &Ingredient,
+) -> std::fmt::Result {
+    if let Some(amount) = &ingredient.amount {
+        write!(f, "*{amount}* ")?;
+    }
+    write!(f, "{}", ingredient.name)?;
+    match group.is_empty() {
+        true => writeln!(f),
+        false => writeln!(f, " ({})", group.join(" > ")),
+    }
+}
+
+fn write_lines(f: &mut std::fmt::Formatter<'_>, lines: &[DiffLine]) -> std::fmt::Result {
+    for line in lines {
+        match line {
+            DiffLine::Unchanged(line) => writeln!(f, "  {line}")?,
+            DiffLine::Added(line) => writeln!(f, "+ {line}")?,
+            DiffLine::Removed(line) => writeln!(f, "- {line}")?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const OLD: &str = "\
+# Cake
+
+A simple cake.
+
+*sweet, baked*
+
+**1 cake**
+
+---
+
+- *200 g* flour
+- *3* eggs
+- *1/2 tsp* salt
+
+## Topping
+
+- *200 ml* cream
+- sugar
+
+---
+
+Mix everything.
+Bake it.
+";
+
+    const NEW: &str = "\
+# Lemon cake
+
+A simple cake.
+
+*sweet, vegan*
+
+**2 cakes**
+
+---
+
+- *250 g* flour
+- *0.5 tsp* Salt
+- *1* lemon
+
+## Topping
+
+- sugar
+
+## Filling
+
+- *200 ml* cream
+
+---
+
+Mix everything.
+Bake it for 30 minutes.
+Let it cool.
+";
+
+    #[test]
+    fn structured_changes() {
+        let old = Recipe::parse(OLD).unwrap();
+        let new = Recipe::parse(NEW).unwrap();
+        let diff = diff(&old, &new);
+
+        assert_eq!(
+            diff.to_string(),
+            "\
+title: Cake -> Lemon cake
++ tag: vegan
+- tag: baked
+yields: 1 cake -> 2 cakes
+~ ingredient: flour: *200 g* -> *250 g*
+- ingredient: *3* eggs
+~ ingredient: cream: moved from Topping to Filling
++ ingredient: *1* lemon
+instructions:
+  Mix everything.
+- Bake it.
++ Bake it for 30 minutes.
++ Let it cool.
+"
+        );
+        let Some(Change::IngredientChanged {
+            old_amount,
+            new_amount,
+            ..
+        }) = diff.changes.get(4)
+        else {
+            panic!("expected a changed ingredient")
+        };
+        assert_eq!(old_amount.as_ref().unwrap().to_string(), "200 g");
+        assert_eq!(new_amount.as_ref().unwrap().to_string(), "250 g");
+    }
+
+    #[test]
+    fn descriptions_and_units() {
+        let old = Recipe::parse("# Tea\n\nHot.\n\n---\n\n- *1 l* water\n- honey\n").unwrap();
+        let new = Recipe::parse("# Tea\n\n---\n\n- *1 liter* water\n- *1 tbsp* honey\n").unwrap();
+
+        assert_eq!(
+            diff(&old, &new).to_string(),
+            "\
+description:
+- Hot.
+~ ingredient: water: *1 l* -> *1 liter*
+~ ingredient: honey: no amount -> *1 tbsp*
+"
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn line_diff() {
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nc\nd"),
+            [
+                DiffLine::Unchanged("a".to_owned()),
+                DiffLine::Removed("b".to_owned()),
+                DiffLine::Unchanged("c".to_owned()),
+                DiffLine::Added("d".to_owned()),
+            ]
+        );
+        assert!(diff_lines("", "").is_empty());
+    }
+}
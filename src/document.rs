@@ -0,0 +1,1137 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Relate a parsed recipe to its markdown source, e.g. for editor integrations.
+
+use std::ops::Range;
+
+use lazy_regex::regex;
+
+use crate::{
+    parser::{DescriptionTagsYields, RecipeParser},
+    Amount, Error, Ingredient, IngredientGroup, ParseOptions, Recipe,
+};
+
+/// A recipe together with its markdown source and the locations of its parts.
+///
+/// Unlike [`Recipe::parse`], creating a document never fails. If the recipe is invalid, the error
+/// is available from [`RecipeDocument::error`] and the source map contains the spans of
+/// everything that was parsed before the error occurred.
+///
+/// ```
+/// # use recipemd::document::RecipeDocument;
+/// let src = "# Pancakes\n\n**4 servings**\n\n---\n\n- *250 ml* [milk](milk.md)\n- *2* eggs\n";
+/// let document = RecipeDocument::parse(src);
+///
+/// let offset = src.find("250").unwrap();
+/// let (ingredient, spans) = document.ingredient_at(offset).unwrap();
+/// assert_eq!(ingredient.name, "milk");
+/// assert_eq!(&src[spans.amount.clone().unwrap()], "250 ml");
+/// assert_eq!(&src[spans.link.clone().unwrap()], "[milk](milk.md)");
+/// ```
+#[derive(Debug)]
+pub struct RecipeDocument {
+    src: String,
+    recipe: Result<Recipe, Error>,
+    source_map: SourceMap,
+    options: ParseOptions,
+}
+
+/// Byte ranges of the parts of a recipe in its markdown source.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    /// The front matter block, including its delimiters.
+    pub front_matter: Option<Range<usize>>,
+    /// The text of the title heading.
+    pub title: Option<Range<usize>>,
+    /// The whole title heading, including its markup.
+    pub title_heading: Option<Range<usize>>,
+    /// The description.
+    pub description: Option<Range<usize>>,
+    /// The emphasized paragraph that contains the tags, including the `*`.
+    pub tags_block: Option<Range<usize>>,
+    /// Each tag, in the order of [`Recipe::tags`].
+    pub tags: Vec<Range<usize>>,
+    /// The strong paragraph that contains the yields, including the `**`.
+    pub yields_block: Option<Range<usize>>,
+    /// Each yield, in the order of [`Recipe::yields`].
+    pub yields: Vec<Range<usize>>,
+    /// The horizontal lines before and after the ingredients.
+    pub dividers: Vec<Range<usize>>,
+    /// Each ingredient, in the order of [`Recipe::all_ingredients`].
+    pub ingredients: Vec<IngredientSpans>,
+    /// Each ingredient group, in the order of the document, i.e. a group is directly followed by
+    /// its nested groups.
+    pub ingredient_groups: Vec<IngredientGroupSpans>,
+    /// The instructions.
+    pub instructions: Option<Range<usize>>,
+}
+
+impl SourceMap {
+    fn for_each_span(&mut self, mut f: impl FnMut(&mut Range<usize>)) {
+        let SourceMap {
+            front_matter,
+            title,
+            title_heading,
+            description,
+            tags_block,
+            tags,
+            yields_block,
+            yields,
+            dividers,
+            ingredients,
+            ingredient_groups,
+            instructions,
+        } = self;
+
+        [
+            front_matter,
+            title,
+            title_heading,
+            description,
+            tags_block,
+            yields_block,
+            instructions,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(tags)
+        .chain(yields)
+        .chain(dividers)
+        .for_each(&mut f);
+        for ingredient in ingredients {
+            f(&mut ingredient.span);
+            ingredient.amount.iter_mut().for_each(&mut f);
+            ingredient.link.iter_mut().for_each(&mut f);
+        }
+        for group in ingredient_groups {
+            f(&mut group.span);
+            f(&mut group.heading);
+            f(&mut group.title);
+        }
+    }
+}
+
+/// The parts of a recipe that can be parsed on their own, see [`RecipeDocument::edit`].
+#[derive(Clone, Copy, Debug)]
+enum Section {
+    Title,
+    Body,
+    Ingredients,
+    Instructions,
+}
+
+/// Byte ranges of the parts of an [`Ingredient`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngredientSpans {
+    /// The whole list item.
+    pub span: Range<usize>,
+    /// The text of the amount, without the surrounding `*`.
+    pub amount: Option<Range<usize>>,
+    /// The link, including its text and destination.
+    pub link: Option<Range<usize>>,
+}
+
+/// Byte ranges of the parts of an [`IngredientGroup`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngredientGroupSpans {
+    /// The heading and all ingredients of the group, including nested groups.
+    pub span: Range<usize>,
+    /// The whole heading, including its markup.
+    pub heading: Range<usize>,
+    /// The text of the heading.
+    pub title: Range<usize>,
+    /// The level of the heading, from 1 to 6.
+    pub level: u8,
+}
+
+/// A part of a recipe that is valid, but probably not what the author intended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub span: Range<usize>,
+}
+
+/// The reason for a [`Lint`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum LintKind {
+    #[error("tag `{0}` is listed more than once")]
+    DuplicateTag(String),
+    #[error("ingredient `{0}` is listed more than once in the same group")]
+    DuplicateIngredient(String),
+    #[error("tag is empty")]
+    EmptyTag,
+}
+
+impl RecipeDocument {
+    /// Parses a recipe, keeping track of the locations of its parts.
+    pub fn parse(src: impl Into<String>) -> Self {
+        Self::parse_with_options(src, &ParseOptions::default())
+    }
+
+    /// Parses a recipe with non-standard options, see [`Recipe::parse_with_options`].
+    pub fn parse_with_options(src: impl Into<String>, options: &ParseOptions) -> Self {
+        let src = src.into();
+        let mut parser = RecipeParser::new(&src).with_options(options.clone());
+        let recipe = parser.parse_recipe();
+        let source_map = std::mem::take(&mut parser.source_map);
+        #[cfg(feature = "diagnostics")]
+        let recipe = recipe.map_err(|e| e.with_src(src.clone()));
+
+        Self {
+            src,
+            recipe,
+            source_map,
+            options: options.clone(),
+        }
+    }
+
+    /// Replaces a byte range of the source and parses the changed part of the recipe again.
+    ///
+    /// A recipe consists of four sections: the front matter and title, the description with
+    /// tags and yields, the ingredients and the instructions. If the edit lies within one
+    /// section and doesn't change where the sections begin and end, only that section is parsed
+    /// again and the results for the other sections are reused. Otherwise, e.g. if a horizontal
+    /// line is edited or the recipe was invalid before, the whole recipe is parsed again. Either
+    /// way, the result is the same as parsing the edited source from scratch.
+    ///
+    /// Returns the updated recipe or the reason why it is invalid.
+    ///
+    /// ```
+    /// # use recipemd::document::RecipeDocument;
+    /// let mut document = RecipeDocument::parse("# Cake\n\n---\n\n- *200 g* flour\n\n---\n\nBake it.\n");
+    ///
+    /// let offset = document.src().find("200").unwrap();
+    /// let recipe = document.edit(offset..offset + 3, "250").unwrap();
+    /// assert_eq!(recipe.ingredients[0].amount.as_ref().unwrap().to_string(), "250 g");
+    ///
+    /// let end = document.src().len();
+    /// assert!(document.edit(end..end, "\n---\n").is_ok());
+    /// assert!(document.edit(0..1, "").is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or doesn't lie on character boundaries.
+    pub fn edit(&mut self, span: Range<usize>, text: &str) -> Result<&Recipe, &Error> {
+        let section = self.section_of(&span);
+        self.src.replace_range(span.clone(), text);
+
+        let reused = section.is_some_and(|section| self.reparse_section(section, span, text.len()));
+        if !reused {
+            *self = Self::parse_with_options(std::mem::take(&mut self.src), &self.options);
+        }
+        self.recipe.as_ref()
+    }
+
+    /// Returns the section that contains the span if it can be parsed on its own.
+    fn section_of(&self, span: &Range<usize>) -> Option<Section> {
+        let recipe = self.recipe.as_ref().ok()?;
+        if has_link_definitions(&self.src) {
+            return None;
+        }
+        let map = &self.source_map;
+        let title = map.title_heading.as_ref()?;
+        let dividers = &map.dividers;
+
+        if span.end <= title.end {
+            return Some(Section::Title);
+        }
+        if line_end(&self.src, title.end) <= span.start && span.end < dividers.first()?.start {
+            return Some(Section::Body);
+        }
+        let ingredients_end = dividers
+            .get(1)
+            .map_or(self.src.len() + 1, |divider| divider.start);
+        if line_end(&self.src, dividers[0].end) <= span.start && span.end < ingredients_end {
+            return Some(Section::Ingredients);
+        }
+        match dividers.get(1) {
+            Some(divider)
+                if line_end(&self.src, divider.end) <= span.start
+                    && recipe.instructions.is_some() =>
+            {
+                Some(Section::Instructions)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses a section after the source was edited and updates the recipe and source map.
+    ///
+    /// `span` is the edited range before the edit, `len` the length of the new text. Returns
+    /// `false` if the whole recipe must be parsed again.
+    fn reparse_section(&mut self, section: Section, span: Range<usize>, len: usize) -> bool {
+        let Ok(recipe) = &mut self.recipe else {
+            return false;
+        };
+        // extensions may keep data from any section
+        if has_link_definitions(&self.src) || !self.options.extensions.is_empty() {
+            return false;
+        }
+        let map = &self.source_map;
+        let (start, old_end) = match section {
+            Section::Title => (0, map.title_heading.as_ref().map_or(0, |title| title.end)),
+            Section::Body => (
+                map.title_heading
+                    .as_ref()
+                    .map_or(0, |title| line_end(&self.src, title.end)),
+                map.dividers[0].end,
+            ),
+            Section::Ingredients => (
+                line_end(&self.src, map.dividers[0].end),
+                map.dividers
+                    .get(1)
+                    .map_or(self.src.len() + span.len() - len, |divider| divider.end),
+            ),
+            Section::Instructions => (
+                line_end(&self.src, map.dividers[1].end),
+                self.src.len() + span.len() - len,
+            ),
+        };
+        let end = old_end + len - span.len();
+        if follows_metadata_fence(&self.src, start) {
+            return false;
+        }
+        let mut parser =
+            RecipeParser::new(&self.src[start..end]).with_options(self.options.clone());
+
+        let mut section_map = match section {
+            Section::Title => {
+                let (Ok(metadata), Ok(title)) = (parser.parse_metadata(), parser.parse_title())
+                else {
+                    return false;
+                };
+                if parser.parse_node().is_some() {
+                    return false;
+                }
+                recipe.metadata = metadata;
+                recipe.title = title;
+                std::mem::take(&mut parser.source_map)
+            }
+            Section::Body => {
+                let Ok(DescriptionTagsYields {
+                    description,
+                    tags,
+                    yields,
+                }) = parser.parse_description_tags_yields()
+                else {
+                    return false;
+                };
+                if parser.parse_node().is_some() {
+                    return false;
+                }
+                recipe.description = description;
+                recipe.tags = tags;
+                recipe.yields = yields;
+                std::mem::take(&mut parser.source_map)
+            }
+            Section::Ingredients => {
+                let Ok((ingredients, ingredient_groups)) = parser.parse_ingredients() else {
+                    return false;
+                };
+                if parser.parse_node().is_some()
+                    || parser.source_map.dividers.len() + 1 != self.source_map.dividers.len()
+                {
+                    return false;
+                }
+                recipe.ingredients = ingredients;
+                recipe.ingredient_groups = ingredient_groups;
+                std::mem::take(&mut parser.source_map)
+            }
+            Section::Instructions => {
+                let instructions = parser.parse_instructions();
+                if instructions.is_none() {
+                    return false;
+                }
+                recipe.instructions = instructions;
+                std::mem::take(&mut parser.source_map)
+            }
+        };
+        section_map.for_each_span(|s| *s = s.start + start..s.end + start);
+
+        // move the spans behind the edit
+        let map = &mut self.source_map;
+        map.for_each_span(|s| {
+            if s.start >= span.end {
+                *s = s.start + len - span.len()..s.end + len - span.len();
+            }
+        });
+        match section {
+            Section::Title => {
+                map.front_matter = section_map.front_matter;
+                map.title = section_map.title;
+                map.title_heading = section_map.title_heading;
+            }
+            Section::Body => {
+                map.description = section_map.description;
+                map.tags_block = section_map.tags_block;
+                map.tags = section_map.tags;
+                map.yields_block = section_map.yields_block;
+                map.yields = section_map.yields;
+                map.dividers[0] = section_map.dividers[0].clone();
+            }
+            Section::Ingredients => {
+                map.ingredients = section_map.ingredients;
+                map.ingredient_groups = section_map.ingredient_groups;
+                if let Some(divider) = section_map.dividers.pop() {
+                    map.dividers[1] = divider;
+                }
+            }
+            Section::Instructions => map.instructions = section_map.instructions,
+        }
+
+        true
+    }
+
+    /// Returns the markdown source of the recipe.
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Returns the recipe, or `None` if it could not be parsed.
+    pub fn recipe(&self) -> Option<&Recipe> {
+        self.recipe.as_ref().ok()
+    }
+
+    /// Returns the reason why the recipe could not be parsed.
+    pub fn error(&self) -> Option<&Error> {
+        self.recipe.as_ref().err()
+    }
+
+    /// Returns the locations of the parts of the recipe.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Returns the ingredient whose list item contains the given byte offset.
+    ///
+    /// Alternatives and ingredients in nested lists are not considered separately.
+    pub fn ingredient_at(&self, offset: usize) -> Option<(&Ingredient, &IngredientSpans)> {
+        self.recipe()?
+            .all_ingredients()
+            .zip(&self.source_map.ingredients)
+            .find(|(_, spans)| contains(&spans.span, offset))
+    }
+
+    /// Returns the yield that contains the given byte offset.
+    pub fn yield_at(&self, offset: usize) -> Option<(&Amount, &Range<usize>)> {
+        self.recipe()?
+            .yields
+            .iter()
+            .zip(&self.source_map.yields)
+            .find(|(_, span)| contains(span, offset))
+    }
+
+    /// Checks the recipe for common mistakes.
+    ///
+    /// Returns no lints if the recipe could not be parsed.
+    ///
+    /// ```
+    /// # use recipemd::document::{LintKind, RecipeDocument};
+    /// let document = RecipeDocument::parse("# Salad\n\n*vegan, quick, vegan*\n\n---\n\n- lettuce\n");
+    /// let lints = document.lints();
+    ///
+    /// assert_eq!(lints[0].kind, LintKind::DuplicateTag("vegan".to_owned()));
+    /// assert_eq!(lints[0].span, 24..29);
+    /// ```
+    pub fn lints(&self) -> Vec<Lint> {
+        let Some(recipe) = self.recipe() else {
+            return Vec::new();
+        };
+        let mut lints = Vec::new();
+
+        for (i, (tag, span)) in recipe.tags.iter().zip(&self.source_map.tags).enumerate() {
+            if tag.is_empty() {
+                lints.push(Lint {
+                    kind: LintKind::EmptyTag,
+                    span: span.clone(),
+                });
+            } else if recipe.tags[..i].iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                lints.push(Lint {
+                    kind: LintKind::DuplicateTag(tag.clone()),
+                    span: span.clone(),
+                });
+            }
+        }
+
+        fn check_group<'r>(
+            ingredients: &'r [Ingredient],
+            groups: &'r [IngredientGroup],
+            spans: &mut impl Iterator<Item = &'r IngredientSpans>,
+            lints: &mut Vec<Lint>,
+        ) {
+            for (i, (ingredient, span)) in ingredients.iter().zip(spans.by_ref()).enumerate() {
+                let name = ingredient.full_name().to_lowercase();
+                if ingredients[..i]
+                    .iter()
+                    .any(|other| other.full_name().to_lowercase() == name)
+                {
+                    lints.push(Lint {
+                        kind: LintKind::DuplicateIngredient(ingredient.full_name().to_owned()),
+                        span: span.span.clone(),
+                    });
+                }
+            }
+            for group in groups {
+                check_group(&group.ingredients, &group.ingredient_groups, spans, lints);
+            }
+        }
+        check_group(
+            &recipe.ingredients,
+            &recipe.ingredient_groups,
+            &mut self.source_map.ingredients.iter(),
+            &mut lints,
+        );
+
+        lints
+    }
+}
+
+/// Returned if a [`RecipeDocument`] could not be edited.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum EditError {
+    #[error("the recipe could not be parsed")]
+    InvalidRecipe,
+    #[error("ingredient group `{}` not found", .0.join(" > "))]
+    GroupNotFound(Vec<String>),
+    #[error("tag `{0}` not found")]
+    TagNotFound(String),
+    #[error("there is no yield at index {0}")]
+    YieldNotFound(usize),
+    #[error("there is no ingredient at index {0}")]
+    IngredientNotFound(usize),
+}
+
+/// Editing the source of a recipe.
+///
+/// Each method returns the updated markdown and only touches the part of the source that
+/// changes, so that formatting and markup elsewhere are kept intact. Parse the result again
+/// to make further edits.
+///
+/// Ingredient groups are identified by the titles of the groups leading to them, e.g.
+/// `&["Topping", "Decoration"]`. An empty path refers to the ingredients outside of any group.
+///
+/// ```
+/// # use recipemd::{document::RecipeDocument, Ingredient};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let document = RecipeDocument::parse("# Cake\n\n---\n\n* *200 g* flour\n\n## Topping\n\n1. *200 ml* cream\n");
+/// let sugar = Ingredient {
+///     amount: Some("2 tbsp".parse()?),
+///     name: "sugar".to_owned(),
+///     link: None,
+///     note: None,
+///     original_name: None,
+///     alternatives: Vec::new(),
+/// };
+///
+/// let src = document.add_ingredient(&["Topping"], &sugar)?;
+/// assert_eq!(src, "# Cake\n\n---\n\n* *200 g* flour\n\n## Topping\n\n1. *200 ml* cream\n2. *2 tbsp* sugar\n");
+///
+/// let document = RecipeDocument::parse(src);
+/// let src = document.set_yield(0, &"1 cake".parse()?)?;
+/// assert_eq!(src, "# Cake\n\n**1 cake**\n\n---\n\n* *200 g* flour\n\n## Topping\n\n1. *200 ml* cream\n2. *2 tbsp* sugar\n");
+/// # Ok(()) }
+/// ```
+impl RecipeDocument {
+    /// Replaces the title.
+    pub fn set_title(&self, title: &str) -> Result<String, EditError> {
+        let span = self
+            .valid_map()?
+            .title
+            .clone()
+            .ok_or(EditError::InvalidRecipe)?;
+        Ok(self.replace(span, title))
+    }
+
+    /// Adds a tag after the existing ones.
+    pub fn add_tag(&self, tag: &str) -> Result<String, EditError> {
+        let map = self.valid_map()?;
+        Ok(match (&map.tags_block, map.tags.last()) {
+            (Some(_), Some(last)) => self.replace(last.end..last.end, &format!(", {tag}")),
+            _ => match &map.yields_block {
+                Some(yields) => self.replace(yields.start..yields.start, &format!("*{tag}*\n\n")),
+                None => self.insert_block(map.description.as_ref(), &format!("*{tag}*")),
+            },
+        })
+    }
+
+    /// Removes the first tag with the given text.
+    pub fn remove_tag(&self, tag: &str) -> Result<String, EditError> {
+        let map = self.valid_map()?;
+        let tags = &self.recipe().ok_or(EditError::InvalidRecipe)?.tags;
+        let index = tags
+            .iter()
+            .position(|t| t == tag)
+            .ok_or_else(|| EditError::TagNotFound(tag.to_owned()))?;
+        let block = map.tags_block.clone().ok_or(EditError::InvalidRecipe)?;
+
+        Ok(self.remove_list_entry(&map.tags, index, block))
+    }
+
+    /// Replaces the yield at the given index, or adds one if the index is the number of yields.
+    pub fn set_yield(&self, index: usize, amount: &Amount) -> Result<String, EditError> {
+        let map = self.valid_map()?;
+        let amount = amount.to_string();
+        Ok(match map.yields.get(index) {
+            Some(span) => self.replace(span.clone(), &amount),
+            None if index == map.yields.len() => match map.yields.last() {
+                Some(last) => self.replace(last.end..last.end, &format!(", {amount}")),
+                None => self.insert_block(
+                    map.tags_block.as_ref().or(map.description.as_ref()),
+                    &format!("**{amount}**"),
+                ),
+            },
+            None => return Err(EditError::YieldNotFound(index)),
+        })
+    }
+
+    /// Removes the yield at the given index.
+    pub fn remove_yield(&self, index: usize) -> Result<String, EditError> {
+        let map = self.valid_map()?;
+        if index >= map.yields.len() {
+            return Err(EditError::YieldNotFound(index));
+        }
+        let block = map.yields_block.clone().ok_or(EditError::InvalidRecipe)?;
+
+        Ok(self.remove_list_entry(&map.yields, index, block))
+    }
+
+    /// Adds an ingredient after the last ingredient of a group.
+    ///
+    /// The new list item uses the same list marker and indentation as the previous one.
+    pub fn add_ingredient(
+        &self,
+        group_path: &[&str],
+        ingredient: &Ingredient,
+    ) -> Result<String, EditError> {
+        let map = self.valid_map()?;
+        let (group, ingredients) = self.find_group(group_path)?;
+        let text = ingredient_markdown(ingredient);
+
+        if !ingredients.is_empty() {
+            let span = &map.ingredients[ingredients.end - 1].span;
+            let line_start = self.src[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let indent = &self.src[line_start..span.start];
+            let indent = match indent.trim().is_empty() {
+                true => indent,
+                false => "",
+            };
+            let marker = match regex!(r"^(\d+)([.)])").captures(&self.src[span.clone()]) {
+                Some(captures) => {
+                    let number: u64 = captures[1].parse().unwrap_or_default();
+                    format!("{}{}", number + 1, &captures[2])
+                }
+                None => self.src[span.start..span.start + 1].to_owned(),
+            };
+            return Ok(self.replace(span.end..span.end, &format!("\n{indent}{marker} {text}")));
+        }
+
+        let after = match group {
+            Some(index) => map.ingredient_groups[index].heading.end,
+            None => map.dividers.first().ok_or(EditError::InvalidRecipe)?.end,
+        };
+        Ok(self.replace(after..after, &format!("\n\n- {text}")))
+    }
+
+    /// Removes the ingredient at the given index, see [`Recipe::all_ingredients`].
+    pub fn remove_ingredient(&self, index: usize) -> Result<String, EditError> {
+        let span = &self
+            .valid_map()?
+            .ingredients
+            .get(index)
+            .ok_or(EditError::IngredientNotFound(index))?
+            .span;
+        let start = self.src[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let rest = &self.src[span.end..];
+        let end = span.end + (rest.len() - rest.trim_start_matches([' ', '\t', '\r']).len());
+        let end = match self.src[end..].starts_with('\n') {
+            true => end + 1,
+            false => end,
+        };
+        Ok(self.replace(start..end, ""))
+    }
+
+    /// Replaces the title of an ingredient group.
+    pub fn rename_group(&self, group_path: &[&str], title: &str) -> Result<String, EditError> {
+        let map = self.valid_map()?;
+        match self.find_group(group_path)? {
+            (Some(index), _) => Ok(self.replace(map.ingredient_groups[index].title.clone(), title)),
+            (None, _) => Err(EditError::GroupNotFound(Vec::new())),
+        }
+    }
+
+    /// Returns the source map if the recipe could be parsed.
+    fn valid_map(&self) -> Result<&SourceMap, EditError> {
+        match self.recipe {
+            Ok(_) => Ok(&self.source_map),
+            Err(_) => Err(EditError::InvalidRecipe),
+        }
+    }
+
+    /// Returns the index of a group in [`SourceMap::ingredient_groups`] and the range of its
+    /// direct ingredients in [`SourceMap::ingredients`].
+    fn find_group(&self, path: &[&str]) -> Result<(Option<usize>, Range<usize>), EditError> {
+        let recipe = self.recipe().ok_or(EditError::InvalidRecipe)?;
+        if path.is_empty() {
+            return Ok((None, 0..recipe.ingredients.len()));
+        }
+
+        struct Walk<'p> {
+            path: &'p [&'p str],
+            group: usize,
+            ingredient: usize,
+        }
+        impl Walk<'_> {
+            fn groups(
+                &mut self,
+                groups: &[IngredientGroup],
+                depth: usize,
+            ) -> Option<(usize, Range<usize>)> {
+                for group in groups {
+                    let index = self.group;
+                    let ingredients = self.ingredient..self.ingredient + group.ingredients.len();
+                    self.group += 1;
+                    self.ingredient = ingredients.end;
+                    let matches = self.path.get(depth) == Some(&group.title.as_str());
+                    if matches && depth + 1 == self.path.len() {
+                        return Some((index, ingredients));
+                    }
+                    let found = self.groups(&group.ingredient_groups, depth + 1);
+                    if matches && found.is_some() {
+                        return found;
+                    }
+                }
+                None
+            }
+        }
+
+        let mut walk = Walk {
+            path,
+            group: 0,
+            ingredient: recipe.ingredients.len(),
+        };
+        match walk.groups(&recipe.ingredient_groups, 0) {
+            Some((index, ingredients)) => Ok((Some(index), ingredients)),
+            None => Err(EditError::GroupNotFound(
+                path.iter().map(|&title| title.to_owned()).collect(),
+            )),
+        }
+    }
+
+    fn replace(&self, span: Range<usize>, text: &str) -> String {
+        let mut src = self.src.clone();
+        src.replace_range(span, text);
+        src
+    }
+
+    /// Inserts a paragraph after the given block, or after the title if there is none.
+    fn insert_block(&self, after: Option<&Range<usize>>, text: &str) -> String {
+        let end = after
+            .or(self.source_map.title_heading.as_ref())
+            .map_or(0, |span| span.end);
+        self.replace(end..end, &format!("\n\n{text}"))
+    }
+
+    /// Removes an entry of a comma separated list of tags or yields, or the whole paragraph if
+    /// it is the only one.
+    fn remove_list_entry(
+        &self,
+        spans: &[Range<usize>],
+        index: usize,
+        block: Range<usize>,
+    ) -> String {
+        if spans.len() == 1 {
+            let rest = &self.src[block.end..];
+            let end = block.end + (rest.len() - rest.trim_start().len());
+            return self.replace(block.start..end, "");
+        }
+        match index {
+            0 => self.replace(spans[0].start..spans[1].start, ""),
+            _ => self.replace(spans[index - 1].end..spans[index].end, ""),
+        }
+    }
+}
+
+/// Returns the markdown of an ingredient without the list marker.
+fn ingredient_markdown(ingredient: &Ingredient) -> String {
+    let name = match &ingredient.link {
+        Some(link) => format!("[{}]({link})", ingredient.full_name()),
+        None => ingredient.full_name().to_owned(),
+    };
+    match &ingredient.amount {
+        Some(amount) => format!("*{amount}* {name}"),
+        None => name,
+    }
+}
+
+/// Returns the position after the rest of the line that ends at the (trimmed) end of a heading
+/// or horizontal line, which is where a full parse continues.
+fn line_end(src: &str, pos: usize) -> usize {
+    let rest = &src[pos..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    let line_break = match trimmed {
+        _ if trimmed.starts_with("\r\n") => 2,
+        _ if trimmed.starts_with('\n') => 1,
+        _ => 0,
+    };
+    pos + rest.len() - trimmed.len() + line_break
+}
+
+/// Returns `true` if a non-blank line starts at `pos` right after a `---` or `+++` line. Together
+/// with a later horizontal line, these lines may be parsed as a metadata block, so a section that
+/// starts there can't be parsed on its own.
+fn follows_metadata_fence(src: &str, pos: usize) -> bool {
+    let Some(previous) = src[..pos].strip_suffix('\n') else {
+        return false;
+    };
+    let previous = previous.strip_suffix('\r').unwrap_or(previous);
+    let previous = &previous[previous.rfind('\n').map_or(0, |i| i + 1)..];
+    let line = src[pos..].split('\n').next().unwrap_or_default();
+    matches!(previous.trim_end(), "---" | "+++") && !line.trim().is_empty()
+}
+
+/// Returns `true` if the source may contain link reference definitions, which can be used
+/// before they are defined and therefore prevent parsing sections on their own.
+fn has_link_definitions(src: &str) -> bool {
+    regex!(r"(?m)^ {0,3}\[[^\]]+\]:").is_match(src)
+}
+
+/// Returns `true` if the offset is inside the span or directly after it.
+fn contains(span: &Range<usize>, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn source_map() {
+        let src = "# Cake\n\n*sweet, baked*\n\n**1 cake, 12 pieces**\n\n---\n\n- *200 g* flour\n\n## Topping\n\n- [cream](cream.md)\n\n### Decoration\n\n- *3* cherries\n\n## Glaze\n\n- sugar\n\n---\n\nBake it.\n";
+        let document = RecipeDocument::parse(src);
+        let map = document.source_map();
+        let text = |span: &Range<usize>| &src[span.clone()];
+
+        assert_eq!(text(map.title.as_ref().unwrap()), "Cake");
+        assert_eq!(text(map.title_heading.as_ref().unwrap()), "# Cake");
+        assert_eq!(map.description, None);
+        assert_eq!(text(map.tags_block.as_ref().unwrap()), "*sweet, baked*");
+        assert_eq!(
+            text(map.yields_block.as_ref().unwrap()),
+            "**1 cake, 12 pieces**"
+        );
+        assert_eq!(
+            map.dividers.iter().map(text).collect::<Vec<_>>(),
+            ["---"; 2]
+        );
+        assert_eq!(text(map.instructions.as_ref().unwrap()), "Bake it.");
+        assert_eq!(
+            map.tags.iter().map(text).collect::<Vec<_>>(),
+            ["sweet", "baked"]
+        );
+        assert_eq!(
+            map.yields.iter().map(text).collect::<Vec<_>>(),
+            ["1 cake", "12 pieces"]
+        );
+        assert_eq!(
+            map.ingredients
+                .iter()
+                .map(|spans| text(&spans.span))
+                .collect::<Vec<_>>(),
+            [
+                "- *200 g* flour",
+                "- [cream](cream.md)",
+                "- *3* cherries",
+                "- sugar"
+            ]
+        );
+        assert_eq!(map.ingredients[1].amount, None);
+        assert_eq!(
+            text(map.ingredients[1].link.as_ref().unwrap()),
+            "[cream](cream.md)"
+        );
+        assert_eq!(
+            map.ingredient_groups
+                .iter()
+                .map(|group| (text(&group.title), text(&group.span), group.level))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "Topping",
+                    "## Topping\n\n- [cream](cream.md)\n\n### Decoration\n\n- *3* cherries",
+                    2
+                ),
+                ("Decoration", "### Decoration\n\n- *3* cherries", 3),
+                ("Glaze", "## Glaze\n\n- sugar", 2),
+            ]
+        );
+        assert_eq!(text(&map.ingredient_groups[1].heading), "### Decoration");
+    }
+
+    #[test]
+    fn partial_source_map() {
+        let document = RecipeDocument::parse("# Cake\n\n---\n\n- *200 g* flour\n- *1*\n");
+
+        assert!(document.recipe().is_none());
+        assert!(document.error().is_some());
+        assert_eq!(document.source_map().ingredients.len(), 1);
+        assert!(document.lints().is_empty());
+    }
+
+    #[test]
+    fn edit_tags_and_yields() {
+        let document = RecipeDocument::parse(
+            "# Cake\n\nA *fine* cake.\n\n*sweet,  baked, easy*\n\n**1 cake**\n\n---\n\n- flour\n",
+        );
+        let amount: Amount = "12 pieces".parse().unwrap();
+
+        assert_eq!(
+            document.add_tag("dessert").unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*sweet,  baked, easy, dessert*\n\n**1 cake**\n\n---\n\n- flour\n"
+        );
+        assert_eq!(
+            document.remove_tag("sweet").unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*baked, easy*\n\n**1 cake**\n\n---\n\n- flour\n"
+        );
+        assert_eq!(
+            document.remove_tag("baked").unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*sweet, easy*\n\n**1 cake**\n\n---\n\n- flour\n"
+        );
+        assert_eq!(
+            document.remove_tag("salty"),
+            Err(EditError::TagNotFound("salty".to_owned()))
+        );
+        assert_eq!(
+            document.set_yield(0, &amount).unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*sweet,  baked, easy*\n\n**12 pieces**\n\n---\n\n- flour\n"
+        );
+        assert_eq!(
+            document.set_yield(1, &amount).unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*sweet,  baked, easy*\n\n**1 cake, 12 pieces**\n\n---\n\n- flour\n"
+        );
+        assert_eq!(
+            document.set_yield(2, &amount),
+            Err(EditError::YieldNotFound(2))
+        );
+        assert_eq!(
+            document.remove_yield(0).unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*sweet,  baked, easy*\n\n---\n\n- flour\n"
+        );
+
+        let document = RecipeDocument::parse("# Cake\n\nA *fine* cake.\n\n---\n\n- flour\n");
+        assert_eq!(
+            document.add_tag("sweet").unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n*sweet*\n\n---\n\n- flour\n"
+        );
+        assert_eq!(
+            document.set_yield(0, &amount).unwrap(),
+            "# Cake\n\nA *fine* cake.\n\n**12 pieces**\n\n---\n\n- flour\n"
+        );
+        let document = RecipeDocument::parse("# Cake\n**1 cake**\n\n---\n");
+        assert_eq!(
+            document.add_tag("sweet").unwrap(),
+            "# Cake\n*sweet*\n\n**1 cake**\n\n---\n"
+        );
+    }
+
+    #[test]
+    fn edit_ingredients() {
+        let src = "# Cake\n\n---\n\n## Dough\n\n  * *200 g* flour\n  * [butter](butter.md)\n    * or margarine\n\n### Filling\n\n3) jam\n\n## Topping\n\n---\n\nBake it.\n";
+        let document = RecipeDocument::parse(src);
+        let egg = Ingredient {
+            amount: Some("1".parse().unwrap()),
+            name: "egg".to_owned(),
+            link: Some("egg.md".to_owned()),
+            note: None,
+            original_name: None,
+            alternatives: Vec::new(),
+        };
+
+        assert_eq!(
+            document.add_ingredient(&[], &egg).unwrap(),
+            src.replace("---\n\n## Dough", "---\n\n- *1* [egg](egg.md)\n\n## Dough")
+        );
+        assert_eq!(
+            document.add_ingredient(&["Dough"], &egg).unwrap(),
+            src.replace("margarine\n", "margarine\n  * *1* [egg](egg.md)\n")
+        );
+        assert_eq!(
+            document
+                .add_ingredient(&["Dough", "Filling"], &egg)
+                .unwrap(),
+            src.replace("3) jam\n", "3) jam\n4) *1* [egg](egg.md)\n")
+        );
+        assert_eq!(
+            document.add_ingredient(&["Topping"], &egg).unwrap(),
+            src.replace("## Topping\n", "## Topping\n\n- *1* [egg](egg.md)\n")
+        );
+        assert_eq!(
+            document.add_ingredient(&["Filling"], &egg),
+            Err(EditError::GroupNotFound(vec!["Filling".to_owned()]))
+        );
+        assert_eq!(
+            document.remove_ingredient(1).unwrap(),
+            src.replace("  * [butter](butter.md)\n    * or margarine\n", "")
+        );
+        assert_eq!(
+            document.remove_ingredient(3),
+            Err(EditError::IngredientNotFound(3))
+        );
+        assert_eq!(
+            document.rename_group(&["Dough", "Filling"], "Jam").unwrap(),
+            src.replace("### Filling", "### Jam")
+        );
+        assert_eq!(
+            document.set_title("Pie").unwrap(),
+            src.replace("# Cake", "# Pie")
+        );
+        assert_eq!(
+            RecipeDocument::parse("# Cake").add_tag("sweet"),
+            Err(EditError::InvalidRecipe)
+        );
+    }
+
+    #[test]
+    fn incremental_edits_after_trailing_whitespace() {
+        let src = "# Cake   \n\nA cake.\n\n---  \n\n- flour\n\n---   \n\nBake it.\n";
+        let edits = [
+            ("A cake.", "New text.", Some("Body")),
+            ("- flour", "- *200 g* flour", Some("Ingredients")),
+            ("Bake it.", "Bake it well.", Some("Instructions")),
+            // the whitespace belongs to the line of the heading or horizontal line
+            ("   \n\nNew", " \n\nNew", None),
+        ];
+
+        let mut document = RecipeDocument::parse(src);
+        for (old, new, section) in edits {
+            let start = document.src().find(old).unwrap();
+            let span = start..start + old.len();
+            assert_eq!(
+                document
+                    .section_of(&span)
+                    .map(|s| format!("{s:?}"))
+                    .as_deref(),
+                section,
+                "{old:?}"
+            );
+            let _ = document.edit(span, new);
+
+            let expected = RecipeDocument::parse(document.src());
+            assert_eq!(document.recipe(), expected.recipe(), "{old:?}");
+            assert_eq!(document.source_map(), expected.source_map(), "{old:?}");
+        }
+        let recipe = document.recipe().unwrap();
+        assert_eq!(recipe.description.as_deref(), Some("New text."));
+        assert_eq!(recipe.instructions.as_deref(), Some("Bake it well."));
+    }
+
+    #[test]
+    #[cfg(feature = "front-matter")]
+    fn incremental_edits() {
+        let mut document = RecipeDocument::parse(
+            "---\nsource: grandma\n---\n# Cake\n\nA cake.\n\n*sweet*\n\n**1 cake**\n\n---\n\n- *200 g* flour\n\n## Topping\n\n- cream\n\n---\n\nBake it.\n",
+        );
+        let edits = [
+            // (old text, new text, section that is parsed again)
+            ("grandma", "grandpa", Some("Title")),
+            ("Cake", "Pie", Some("Title")),
+            ("A cake.", "A *fine* pie.\n\nReally.", Some("Body")),
+            ("*sweet*", "*sweet, baked*", Some("Body")),
+            ("1 cake", "1 pie, 12 pieces", Some("Body")),
+            ("200 g", "250 g", Some("Ingredients")),
+            (
+                "- cream",
+                "- cream\n\n### Decoration\n\n- *3* cherries",
+                Some("Ingredients"),
+            ),
+            ("Bake it.", "Bake it.\n\nEat it.", Some("Instructions")),
+            // changes the structure
+            ("- cream", "- cream\n\n---\n", Some("Ingredients")),
+            ("- *3* cherries", "- *3* eggs", Some("Instructions")),
+            ("- *250 g* flour", "- *1*", Some("Ingredients")),
+            ("- *1*", "- *1* egg", None),
+            (
+                "Eat it.",
+                "Eat it.\n\n[cream]: cream.md",
+                Some("Instructions"),
+            ),
+            ("- cream", "- [cream]", None),
+        ];
+
+        for (old, new, section) in edits {
+            let start = document.src().find(old).unwrap();
+            let span = start..start + old.len();
+            assert_eq!(
+                document
+                    .section_of(&span)
+                    .map(|s| format!("{s:?}"))
+                    .as_deref(),
+                section,
+                "{old:?}"
+            );
+            let _ = document.edit(span, new);
+
+            let expected = RecipeDocument::parse(document.src());
+            assert_eq!(document.recipe(), expected.recipe(), "{old:?}");
+            assert_eq!(
+                document.error().map(|e| &e.kind),
+                expected.error().map(|e| &e.kind)
+            );
+            assert_eq!(document.source_map(), expected.source_map(), "{old:?}");
+        }
+    }
+
+    #[test]
+    fn edits_match_full_parse() {
+        let sources = [
+            "# Cake\n\nA cake.\n\n---\n\n- *200 g* flour\n\n## Topping\n\n- cream\n\n---\n\nBake it.\n",
+            "# Cake  \n*sweet*\n---  \n- *200 g* flour\n---\nBake it.\n---\n",
+        ];
+        // a line right after a horizontal line can start a metadata block
+        let texts = [
+            "## X\n", "* a\n", "x", "\n", "---\n", "+++\n", "# T\n", "\n\n",
+        ];
+
+        let mut failures = Vec::new();
+        for src in sources {
+            for text in texts {
+                for offset in 0..=src.len() {
+                    let mut document = RecipeDocument::parse(src);
+                    let _ = document.edit(offset..offset, text);
+
+                    let expected = RecipeDocument::parse(document.src());
+                    if document.recipe() != expected.recipe()
+                        || document.error().map(|e| &e.kind) != expected.error().map(|e| &e.kind)
+                        || document.source_map() != expected.source_map()
+                    {
+                        failures.push((src, offset, text));
+                    }
+                }
+            }
+        }
+        assert_eq!(failures, []);
+    }
+
+    #[test]
+    fn lints() {
+        let src =
+            "# Cake\n\n*sweet, , Sweet*\n\n---\n\n- flour\n- Flour\n\n## Topping\n\n- flour\n";
+        let document = RecipeDocument::parse(src);
+
+        assert_eq!(
+            document
+                .lints()
+                .into_iter()
+                .map(|lint| (lint.kind, &src[lint.span]))
+                .collect::<Vec<_>>(),
+            [
+                (LintKind::EmptyTag, ""),
+                (LintKind::DuplicateTag("Sweet".to_owned()), "Sweet"),
+                (LintKind::DuplicateIngredient("Flour".to_owned()), "- Flour"),
+            ]
+        );
+    }
+}
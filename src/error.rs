@@ -23,6 +23,12 @@ pub enum ErrorKind {
     EmptyIngredient,
     #[error("amount is missing a value")]
     AmountWithoutValue,
+    #[error("invalid front matter: {0}")]
+    InvalidMetadata(String),
+    #[error("expected a list of ingredients")]
+    ExpectedIngredientList,
+    #[error("invalid extension data: {0}")]
+    InvalidExtensionData(String),
 }
 
 /// Returned if a parsing a recipe was not successful.
@@ -0,0 +1,422 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Recognize additional constructs in recipes and attach typed data to the parsed [`Recipe`].
+//!
+//! An [`Extension`] is registered with [`ParseOptions::extension`] and gets the chance to
+//! consume blocks of the recipe before they are parsed according to the RecipeMD
+//! specification. Everything an extension doesn't consume is parsed as usual, so recipes that
+//! don't use the extension's syntax result in the same [`Recipe`] as without it.
+//!
+//! ```
+//! # use recipemd::{ast::{Node, NodeKind}, extension::{Extension, Extensions}, ParseOptions, Recipe};
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct Source(String);
+//!
+//! /// Parses a `> Source: ...` block quote.
+//! #[derive(Debug)]
+//! struct SourceExtension;
+//!
+//! impl Extension for SourceExtension {
+//!     fn parse_body_block(
+//!         &self,
+//!         src: &str,
+//!         node: &Node<'_>,
+//!         data: &mut Extensions,
+//!     ) -> Result<bool, String> {
+//!         let text = &src[node.span.clone()];
+//!         match (&node.kind, text.strip_prefix("> Source:")) {
+//!             (NodeKind::Other, Some(source)) => {
+//!                 data.insert(Source(source.trim().to_owned()));
+//!                 Ok(true)
+//!             }
+//!             _ => Ok(false),
+//!         }
+//!     }
+//! }
+//!
+//! # fn main() -> recipemd::Result<()> {
+//! let src = "# Cake\n\nA simple cake.\n\n> Source: grandma\n\n---\n\n- *200 g* flour\n";
+//! let options = ParseOptions::new().extension(SourceExtension);
+//! let recipe = Recipe::parse_with_options(src, &options)?;
+//!
+//! assert_eq!(recipe.description.as_deref(), Some("A simple cake."));
+//! assert_eq!(recipe.extensions.get::<Source>(), Some(&Source("grandma".to_owned())));
+//! # Ok(()) }
+//! ```
+//!
+//! [`ParseOptions::extension`]: crate::ParseOptions::extension
+//! [`Recipe`]: crate::Recipe
+
+use std::{
+    any::{Any, TypeId},
+    collections::BTreeMap,
+    fmt::Debug,
+};
+
+use crate::{ast::Node, IngredientGroup};
+
+/// Hooks into the parser to recognize additional constructs.
+///
+/// Each method returns `Ok(true)` if the extension consumed the block, which is then skipped by
+/// the parser and by all other extensions. Extensions are called in the order in which they
+/// were registered. An `Err` aborts parsing with
+/// [`ErrorKind::InvalidExtensionData`](crate::ErrorKind::InvalidExtensionData) at the span of
+/// the block.
+///
+/// All methods are optional and don't consume anything by default.
+pub trait Extension: Debug + Send + Sync {
+    /// Called for each block between the title and the first horizontal line, before it is
+    /// parsed as part of the description, the tags or the yields.
+    ///
+    /// A consumed block ends the description like a tags or yields paragraph does.
+    fn parse_body_block(
+        &self,
+        src: &str,
+        node: &Node<'_>,
+        data: &mut Extensions,
+    ) -> Result<bool, String> {
+        let _ = (src, node, data);
+        Ok(false)
+    }
+
+    /// Called for each parsed ingredient group, including nested groups. A consumed group is
+    /// removed from the recipe, e.g. to collect an `Equipment` list separately.
+    fn parse_ingredient_group(
+        &self,
+        src: &str,
+        group: &IngredientGroup,
+        data: &mut Extensions,
+    ) -> Result<bool, String> {
+        let _ = (src, group, data);
+        Ok(false)
+    }
+
+    /// Called for each block in the ingredients section that is neither a list, a heading nor a
+    /// horizontal line and would otherwise be an error.
+    fn parse_ingredients_block(
+        &self,
+        src: &str,
+        node: &Node<'_>,
+        data: &mut Extensions,
+    ) -> Result<bool, String> {
+        let _ = (src, node, data);
+        Ok(false)
+    }
+}
+
+/// A map that holds at most one value of each type, used for the data of [`Extension`]s.
+#[derive(Default)]
+pub struct Extensions {
+    map: BTreeMap<TypeId, Box<dyn ExtensionValue>>,
+}
+
+impl Extensions {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, returning the previous value of the same type.
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Clone + Debug + PartialEq + Send + Sync + 'static,
+    {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|value| value.into_any().downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns a reference to the value of type `T`.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.as_any().downcast_ref())
+    }
+
+    /// Returns a mutable reference to the value of type `T`.
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.as_any_mut().downcast_mut())
+    }
+
+    /// Returns a mutable reference to the value of type `T`, inserting the default value if
+    /// there is none yet.
+    pub fn get_or_default<T>(&mut self) -> &mut T
+    where
+        T: Clone + Debug + Default + PartialEq + Send + Sync + 'static,
+    {
+        self.map
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .as_any_mut()
+            .downcast_mut()
+            .expect("values are stored by their type id")
+    }
+
+    /// Removes and returns the value of type `T`.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.into_any().downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns the number of values in the map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Clone for Extensions {
+    fn clone(&self) -> Self {
+        Self {
+            map: self
+                .map
+                .iter()
+                .map(|(id, value)| (*id, value.clone_box()))
+                .collect(),
+        }
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.map.values()).finish()
+    }
+}
+
+impl PartialEq for Extensions {
+    fn eq(&self, other: &Self) -> bool {
+        self.map.len() == other.map.len()
+            && self
+                .map
+                .iter()
+                .zip(&other.map)
+                .all(|((a_id, a), (b_id, b))| a_id == b_id && a.eq_dyn(b.as_any()))
+    }
+}
+
+/// The operations that are needed to clone, compare and debug the values of [`Extensions`].
+trait ExtensionValue: Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn clone_box(&self) -> Box<dyn ExtensionValue>;
+    fn eq_dyn(&self, other: &dyn Any) -> bool;
+}
+
+impl<T> ExtensionValue for T
+where
+    T: Clone + Debug + PartialEq + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn ExtensionValue> {
+        Box::new(self.clone())
+    }
+    fn eq_dyn(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<T>() == Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{ast::NodeKind, ErrorKind, ParseOptions, Recipe};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Source(String);
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Equipment(Vec<String>);
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Nutrition(Vec<(String, String)>);
+
+    #[derive(Debug)]
+    struct TestExtension;
+
+    impl Extension for TestExtension {
+        fn parse_body_block(
+            &self,
+            src: &str,
+            node: &Node<'_>,
+            data: &mut Extensions,
+        ) -> Result<bool, String> {
+            let text = &src[node.span.clone()];
+            match (&node.kind, text.strip_prefix("> Source:")) {
+                (NodeKind::Other, Some(source)) => {
+                    data.insert(Source(source.trim().to_owned()));
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        }
+
+        fn parse_ingredient_group(
+            &self,
+            _src: &str,
+            group: &IngredientGroup,
+            data: &mut Extensions,
+        ) -> Result<bool, String> {
+            if group.title != "Equipment" {
+                return Ok(false);
+            }
+            let equipment = data.get_or_default::<Equipment>();
+            equipment
+                .0
+                .extend(group.ingredients.iter().map(|item| item.name.clone()));
+            Ok(true)
+        }
+
+        fn parse_ingredients_block(
+            &self,
+            src: &str,
+            node: &Node<'_>,
+            data: &mut Extensions,
+        ) -> Result<bool, String> {
+            let text = src[node.span.clone()].trim();
+            if !text.starts_with('|') {
+                return Ok(false);
+            }
+            let rows = text
+                .lines()
+                .skip(2)
+                .map(
+                    |row| match row.trim_matches('|').split('|').collect::<Vec<_>>()[..] {
+                        [name, value] => Ok((name.trim().to_owned(), value.trim().to_owned())),
+                        _ => Err(format!("invalid nutrition row `{row}`")),
+                    },
+                )
+                .collect::<Result<_, _>>()?;
+            data.insert(Nutrition(rows));
+            Ok(true)
+        }
+    }
+
+    const SRC: &str = "\
+# Cake
+
+A simple cake.
+
+> Source: grandma
+
+*sweet*
+
+---
+
+- *200 g* flour
+
+## Equipment
+
+- bowl
+- oven
+
+## Topping
+
+- *100 ml* cream
+
+| Nutrient | Amount |
+| -------- | ------ |
+| Energy   | 300 kcal |
+
+---
+
+Bake it.
+";
+
+    #[test]
+    fn extension_data() {
+        let options = ParseOptions::new().extension(TestExtension);
+        let recipe = Recipe::parse_with_options(SRC, &options).unwrap();
+
+        assert_eq!(recipe.description.as_deref(), Some("A simple cake."));
+        assert_eq!(recipe.tags, ["sweet"]);
+        assert_eq!(recipe.ingredients[0].name, "flour");
+        assert_eq!(recipe.ingredient_groups.len(), 1);
+        assert_eq!(recipe.ingredient_groups[0].title, "Topping");
+        assert_eq!(recipe.instructions.as_deref(), Some("Bake it."));
+
+        assert_eq!(recipe.extensions.len(), 3);
+        assert_eq!(
+            recipe.extensions.get::<Source>(),
+            Some(&Source("grandma".to_owned()))
+        );
+        assert_eq!(
+            recipe.extensions.get::<Equipment>(),
+            Some(&Equipment(vec!["bowl".to_owned(), "oven".to_owned()]))
+        );
+        assert_eq!(
+            recipe.extensions.get::<Nutrition>(),
+            Some(&Nutrition(vec![(
+                "Energy".to_owned(),
+                "300 kcal".to_owned()
+            )]))
+        );
+        assert_eq!(recipe.clone().extensions, recipe.extensions);
+    }
+
+    #[test]
+    fn without_extension() {
+        let recipe = Recipe::parse(&SRC.replace("| ", "").replace(" |", "")).unwrap_err();
+        assert_eq!(recipe.kind, ErrorKind::ExpectedHorizontalLine);
+
+        let src = SRC.split("\n|").next().unwrap();
+        let recipe = Recipe::parse(&format!("{src}\n---\n")).unwrap();
+        assert!(recipe.extensions.is_empty());
+        assert_eq!(
+            recipe.description.as_deref(),
+            Some("A simple cake.\n\n> Source: grandma")
+        );
+        assert_eq!(recipe.ingredient_groups[0].title, "Equipment");
+    }
+
+    #[test]
+    fn extension_errors() {
+        let src = SRC.replace("| Energy   | 300 kcal |", "| Energy |");
+        let options = ParseOptions::new().extension(TestExtension);
+        let error = Recipe::parse_with_options(&src, &options).unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            ErrorKind::InvalidExtensionData("invalid nutrition row `| Energy |`".to_owned())
+        );
+        assert_eq!(
+            error.span,
+            Some(src.find("| Nutrient").unwrap()..src.find("\n---\n\nBake").unwrap())
+        );
+    }
+
+    #[test]
+    fn typed_map() {
+        let mut map = Extensions::new();
+        assert_eq!(map.insert(Source("a".to_owned())), None);
+        assert_eq!(
+            map.insert(Source("b".to_owned())),
+            Some(Source("a".to_owned()))
+        );
+        map.get_mut::<Source>().unwrap().0.push('c');
+        assert_eq!(map.get::<Source>(), Some(&Source("bc".to_owned())));
+        assert_eq!(map.get::<Equipment>(), None);
+        assert_eq!(format!("{map:?}"), r#"{Source("bc")}"#);
+        assert_eq!(map.remove::<Source>(), Some(Source("bc".to_owned())));
+        assert!(map.is_empty());
+    }
+}
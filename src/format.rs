@@ -0,0 +1,287 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Rewrite recipes into a canonical markdown layout.
+
+use lazy_regex::regex;
+
+use crate::{document::RecipeDocument, Error, IngredientGroup, Recipe};
+
+/// Returned if a recipe could not be formatted.
+#[derive(Debug, thiserror::Error)]
+pub enum FormatError {
+    #[error(transparent)]
+    Parse(#[from] Error),
+    /// The formatted recipe would not parse to the same recipe as the original.
+    #[error("formatting would change the content of the recipe")]
+    ContentChanged,
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for FormatError {
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            FormatError::Parse(e) => e.source_code(),
+            FormatError::ContentChanged => None,
+        }
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            FormatError::Parse(e) => e.labels(),
+            FormatError::ContentChanged => None,
+        }
+    }
+}
+
+/// Formats a recipe in a canonical layout.
+///
+/// - the title is a first level ATX heading (`# Title`),
+/// - tags and yields are separated by `, `,
+/// - both horizontal lines are written as `---`,
+/// - ingredients use `-` as list marker and are followed by a single space after the amount,
+/// - ingredient groups use ATX headings whose level reflects their nesting below the title,
+/// - blocks are separated by a single empty line and the file ends with a newline.
+///
+/// Front matter, the description, the instructions and the names of ingredients are kept as
+/// they are. Formatting is idempotent, and the result is reparsed to make sure that it contains
+/// exactly the same recipe as the original. If it doesn't, [`FormatError::ContentChanged`] is
+/// returned.
+///
+/// ```
+/// # use recipemd::format::format;
+/// # fn main() -> Result<(), recipemd::format::FormatError> {
+/// let src = "Pancakes\n========\n\n*sweet,quick*\n\n***\n\n* *250 ml*milk\n+ *2* eggs\n\n# Topping\n1. syrup\n";
+///
+/// assert_eq!(
+///     format(src)?,
+///     "# Pancakes\n\n*sweet, quick*\n\n---\n\n- *250 ml* milk\n- *2* eggs\n\n## Topping\n\n- syrup\n"
+/// );
+/// # Ok(()) }
+/// ```
+pub fn format(src: &str) -> Result<String, FormatError> {
+    let original = Recipe::parse(src)?;
+    let document = RecipeDocument::parse(src);
+    let formatted = Formatter::new(&document, &original).format();
+
+    let reparsed = Recipe::parse(&formatted).map_err(|_| FormatError::ContentChanged)?;
+    if original != reparsed {
+        return Err(FormatError::ContentChanged);
+    }
+
+    Ok(formatted)
+}
+
+/// Collects the blocks of the formatted recipe.
+struct Formatter<'a> {
+    src: &'a str,
+    recipe: &'a Recipe,
+    document: &'a RecipeDocument,
+    /// The index of the next ingredient in the source map.
+    ingredient: usize,
+    blocks: Vec<String>,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(document: &'a RecipeDocument, recipe: &'a Recipe) -> Self {
+        Self {
+            src: document.src(),
+            recipe,
+            document,
+            ingredient: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    fn format(mut self) -> String {
+        let map = self.document.source_map();
+        let recipe = self.recipe;
+
+        // everything before the title can only be front matter
+        let title_start = map.title.as_ref().map_or(0, |span| span.start);
+        let line_start = self.src[..title_start].rfind('\n').map_or(0, |i| i + 1);
+        let front_matter = self.src[..line_start].trim();
+        if !front_matter.is_empty() {
+            self.blocks.push(front_matter.to_owned());
+        }
+
+        self.blocks.push(format!("# {}", recipe.title));
+        if let Some(description) = &recipe.description {
+            self.blocks.push(description.clone());
+        }
+        if !recipe.tags.is_empty() {
+            self.blocks.push(format!("*{}*", recipe.tags.join(", ")));
+        }
+        if !map.yields.is_empty() {
+            let yields: Vec<&str> = map
+                .yields
+                .iter()
+                .map(|span| &self.src[span.clone()])
+                .collect();
+            self.blocks.push(format!("**{}**", yields.join(", ")));
+        }
+        self.blocks.push("---".to_owned());
+
+        self.ingredients(recipe.ingredients.len());
+        for group in &recipe.ingredient_groups {
+            self.ingredient_group(group, 2);
+        }
+
+        if let Some(instructions) = &recipe.instructions {
+            self.blocks.push("---".to_owned());
+            self.blocks.push(instructions.clone());
+        }
+
+        let mut out = self.blocks.join("\n\n");
+        out.push('\n');
+        out
+    }
+
+    fn ingredient_group(&mut self, group: &IngredientGroup, level: usize) {
+        self.blocks
+            .push(format!("{} {}", "#".repeat(level), group.title));
+        self.ingredients(group.ingredients.len());
+        for group in &group.ingredient_groups {
+            self.ingredient_group(group, level + 1);
+        }
+    }
+
+    /// Adds a list of the next `count` ingredients.
+    fn ingredients(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let items = &self.document.source_map().ingredients[self.ingredient..][..count];
+        let list: Vec<String> = items
+            .iter()
+            .map(|spans| {
+                let item = &self.src[spans.span.clone()];
+                let rest = match &spans.amount {
+                    // skip the closing `*` or `_` of the amount
+                    Some(amount) => &self.src[amount.end + 1..spans.span.end],
+                    None => {
+                        let marker = regex!(r"^(?:[-*+]|\d+[.)])").find(item);
+                        &item[marker.map_or(0, |m| m.end())..]
+                    }
+                };
+                let rest = rest.trim_start_matches([' ', '\t']);
+                match &spans.amount {
+                    Some(amount) => format!("- *{}* {rest}", self.src[amount.clone()].trim()),
+                    None => format!("- {rest}"),
+                }
+            })
+            .collect();
+        self.ingredient += count;
+        self.blocks.push(list.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn canonical_layout() {
+        let src = "\
+Cake
+====
+A *simple*   cake.
+
+Really.
+
+**1 cake,12 pieces**
+
+*sweet ,baked*
+
+- - -
+* *200  g*flour
+* _3_    eggs
+1. [butter](butter.md)
+   - or margarine
+
+Topping
+-------
+### Cream
++ *200 ml*cream
+
+# Glaze
+
++ sugar
+___
+
+Mix   everything.
+Bake it.
+
+
+
+";
+        let formatted = format(src).unwrap();
+
+        assert_eq!(
+            formatted,
+            "\
+# Cake
+
+A *simple*   cake.
+
+Really.
+
+*sweet, baked*
+
+**1 cake, 12 pieces**
+
+---
+
+- *200  g* flour
+- *3* eggs
+- [butter](butter.md)
+   - or margarine
+
+## Topping
+
+### Cream
+
+- *200 ml* cream
+
+## Glaze
+
+- sugar
+
+---
+
+Mix   everything.
+Bake it.
+"
+        );
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    #[cfg(feature = "front-matter")]
+    fn front_matter() {
+        assert_eq!(
+            format("---\nsource: grandma\n---\nCake\n====\n---\n").unwrap(),
+            "---\nsource: grandma\n---\n\n# Cake\n\n---\n"
+        );
+    }
+
+    #[test]
+    fn minimal_recipes() {
+        assert_eq!(format("# Water\n---").unwrap(), "# Water\n\n---\n");
+        assert_eq!(
+            format("# Water\n\n---\n\n---\n\nDrink it.").unwrap(),
+            "# Water\n\n---\n\n---\n\nDrink it.\n"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(format("Water"), Err(FormatError::Parse(_))));
+        // the closing sequence of the heading would be removed
+        assert!(matches!(
+            format("Water #\n======\n\n---\n"),
+            Err(FormatError::ContentChanged)
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! A structured view of a recipe's instructions.
+
+use std::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ast::{Node, NodeKind, NodeList},
+    parser::RecipeParser,
+    utils::{strip_markdown, TrimNewlines},
+    Recipe,
+};
+
+/// A single step of a recipe's instructions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Step {
+    /// The position of the step, starting at 1.
+    pub number: usize,
+    /// The text of the heading that introduced the step, if any.
+    pub title: Option<String>,
+    /// The byte range of the step within the instructions.
+    pub span: Range<usize>,
+    /// The markdown source of the step.
+    pub source: String,
+    /// The plain text of the step without any markup.
+    pub text: String,
+}
+
+impl Recipe {
+    /// Splits the instructions into individual steps.
+    ///
+    /// See [`steps`] for details.
+    pub fn steps(&self) -> Vec<Step> {
+        self.instructions.as_deref().map(steps).unwrap_or_default()
+    }
+}
+
+/// Splits markdown instructions into individual steps.
+///
+/// If the instructions contain headings, each heading starts a new step that includes all
+/// content until the next heading. Otherwise, each item of a list and each paragraph is a
+/// separate step. Other blocks like code blocks or block quotes belong to the preceding step.
+///
+/// ```
+/// # use recipemd::instructions::steps;
+/// let steps = steps("Preheat the oven.\n\n1. Mix *everything*.\n2. Bake.\n");
+///
+/// assert_eq!(steps.len(), 3);
+/// assert_eq!(steps[1].source, "Mix *everything*.");
+/// assert_eq!(steps[1].text, "Mix everything.");
+/// assert_eq!(steps[1].span, 22..39);
+/// ```
+pub fn steps(src: &str) -> Vec<Step> {
+    let mut parser = RecipeParser::new(src);
+    let nodes: Vec<Node> = std::iter::from_fn(|| parser.parse_node()).collect();
+
+    let mut spans: Vec<(Option<String>, Range<usize>)> = Vec::new();
+
+    if nodes
+        .iter()
+        .any(|node| matches!(node.kind, NodeKind::Heading { .. }))
+    {
+        for node in &nodes {
+            match &node.kind {
+                NodeKind::Heading { children, .. } => {
+                    let title = match children.is_empty() {
+                        true => String::new(),
+                        false => src[children.span()].to_owned(),
+                    };
+                    spans.push((Some(title), node.span.clone()));
+                }
+                _ => match spans.last_mut() {
+                    Some((_, span)) => span.end = node.span.end,
+                    None => spans.push((None, node.span.clone())),
+                },
+            }
+        }
+    } else {
+        for node in &nodes {
+            match &node.kind {
+                NodeKind::List(items) => spans.extend(
+                    items
+                        .iter()
+                        .filter_map(|item| match &item.kind {
+                            NodeKind::ListItem(children) if !children.is_empty() => {
+                                Some(children.span())
+                            }
+                            _ => None,
+                        })
+                        .map(|span| (None, span)),
+                ),
+                NodeKind::Paragraph(_) => spans.push((None, node.span.clone())),
+                NodeKind::HorizontalLine => (),
+                _ => match spans.last_mut() {
+                    Some((_, span)) => span.end = node.span.end,
+                    None => spans.push((None, node.span.clone())),
+                },
+            }
+        }
+    }
+
+    spans
+        .into_iter()
+        .filter_map(|(title, span)| {
+            let raw = &src[span.clone()];
+            let source = raw.trim_newlines();
+            if source.trim().is_empty() {
+                return None;
+            }
+            let start = span.start + raw.len() - raw.trim_start_matches(['\n', '\r']).len();
+            Some((title, start..start + source.len()))
+        })
+        .enumerate()
+        .map(|(i, (title, span))| Step {
+            number: i + 1,
+            title,
+            source: src[span.clone()].to_owned(),
+            text: strip_markdown(&src[span.clone()]),
+            span,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn steps_from_paragraphs_and_lists() {
+        let src = "Preheat the oven.\n\n```\ncode\n```\n\n- Mix\n- Bake\n  for a while\n";
+
+        assert_eq!(
+            steps(src),
+            vec![
+                Step {
+                    number: 1,
+                    title: None,
+                    span: 0..31,
+                    source: "Preheat the oven.\n\n```\ncode\n```".to_owned(),
+                    text: "Preheat the oven.\n\ncode".to_owned(),
+                },
+                Step {
+                    number: 2,
+                    title: None,
+                    span: 35..38,
+                    source: "Mix".to_owned(),
+                    text: "Mix".to_owned(),
+                },
+                Step {
+                    number: 3,
+                    title: None,
+                    span: 41..59,
+                    source: "Bake\n  for a while".to_owned(),
+                    text: "Bake for a while".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_from_headings() {
+        let src = "Intro.\n\n## Dough\n\nKnead.\n\n- Rest\n\n## Filling\n\nStir.\n";
+        let steps = steps(src);
+
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].title, None);
+        assert_eq!(steps[0].source, "Intro.");
+        assert_eq!(steps[1].title.as_deref(), Some("Dough"));
+        assert_eq!(steps[1].source, "## Dough\n\nKnead.\n\n- Rest");
+        assert_eq!(steps[1].text, "Dough\n\nKnead.\n\nRest");
+        assert_eq!(steps[2].title.as_deref(), Some("Filling"));
+        assert_eq!(steps[2].number, 3);
+    }
+}
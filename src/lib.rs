@@ -44,11 +44,33 @@
 
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
-mod ast;
+pub mod ast;
+pub mod collection;
+pub mod cst;
+pub mod diff;
+pub mod document;
 mod error;
+pub mod extension;
+pub mod format;
+pub mod instructions;
+pub mod mealplan;
+pub mod mentions;
+mod metadata;
 mod models;
+pub mod normalize;
+mod options;
+pub mod pantry;
 mod parser;
+pub mod query;
+pub mod render;
+mod scaling;
+#[cfg(feature = "search")]
+pub mod search;
+pub mod temperatures;
+pub mod timers;
+pub mod units;
 mod utils;
+pub mod variants;
 
 use std::str::FromStr;
 
@@ -56,6 +78,8 @@ use std::str::FromStr;
 pub use error::*;
 #[doc(inline)]
 pub use models::*;
+#[doc(inline)]
+pub use options::{ParseOptions, SublistMode};
 use parser::RecipeParser;
 
 impl Recipe {
@@ -70,6 +94,84 @@ impl Recipe {
     pub fn parse(src: &str) -> Result<Self> {
         RecipeParser::new(src).parse_recipe()
     }
+
+    /// Parse a recipe from a markdown string, using non-standard [`ParseOptions`].
+    ///
+    /// ```
+    /// # use recipemd::{ParseOptions, Recipe};
+    /// # fn main() -> recipemd::Result<()> {
+    /// let options = ParseOptions::new().split_notes(true);
+    /// let recipe = Recipe::parse_with_options("# Soup\n\n---\n\n- *1* onion, finely chopped\n", &options)?;
+    ///
+    /// assert_eq!(recipe.ingredients[0].name, "onion");
+    /// assert_eq!(recipe.ingredients[0].note.as_deref(), Some("finely chopped"));
+    /// assert_eq!(recipe.ingredients[0].full_name(), "onion, finely chopped");
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "diagnostics")]
+    pub fn parse_with_options(src: &str, options: &ParseOptions) -> Result<Self> {
+        RecipeParser::new(src)
+            .with_options(options.clone())
+            .parse_recipe()
+            .map_err(|e| e.with_src(src.to_owned()))
+    }
+    /// Parse a recipe from a markdown string, using non-standard [`ParseOptions`].
+    #[cfg(not(feature = "diagnostics"))]
+    pub fn parse_with_options(src: &str, options: &ParseOptions) -> Result<Self> {
+        RecipeParser::new(src)
+            .with_options(options.clone())
+            .parse_recipe()
+    }
+}
+
+impl Recipe {
+    /// Parse all recipes of a markdown document that contains several of them.
+    ///
+    /// The document is split at first level headings, except for those that are ingredient
+    /// groups (between the first and the second horizontal line of a recipe). A recipe without
+    /// instructions that is followed by another one therefore needs to end with a second
+    /// horizontal line, and first level headings can't be used in the instructions. Use
+    /// [`ParseOptions::recipe_separator`] to split at an explicit separator instead.
+    ///
+    /// Each part is parsed on its own, so an invalid recipe doesn't affect the others.
+    ///
+    /// ```
+    /// # use recipemd::Recipe;
+    /// let src = "# Tea\n\n---\n\n- *1* tea bag\n\n---\n\nSteep.\n\n# Coffee\n\n# Water\n\n---\n";
+    /// let recipes = Recipe::parse_many(src);
+    ///
+    /// assert_eq!(recipes.len(), 3);
+    /// assert_eq!(recipes[0].recipe.as_ref().unwrap().title, "Tea");
+    /// assert!(recipes[1].recipe.is_err());
+    /// assert_eq!(&src[recipes[2].span.clone()], "# Water\n\n---\n");
+    /// ```
+    pub fn parse_many(src: &str) -> Vec<ParsedRecipe> {
+        Self::parse_many_with_options(src, &ParseOptions::default())
+    }
+
+    /// Parse all recipes of a markdown document, using non-standard [`ParseOptions`].
+    ///
+    /// See [`Recipe::parse_many`] for details.
+    pub fn parse_many_with_options(src: &str, options: &ParseOptions) -> Vec<ParsedRecipe> {
+        parser::split_recipes(src, options.recipe_separator.as_deref())
+            .into_iter()
+            .map(|span| {
+                let recipe = RecipeParser::new(&src[span.clone()])
+                    .with_options(options.clone())
+                    .parse_recipe()
+                    .map_err(|mut e| {
+                        e.span = Some(match e.span {
+                            Some(s) => s.start + span.start..s.end + span.start,
+                            None => span.end..span.end,
+                        });
+                        #[cfg(feature = "diagnostics")]
+                        let e = e.with_src(src.to_owned());
+                        e
+                    });
+                ParsedRecipe { span, recipe }
+            })
+            .collect()
+    }
 }
 
 impl FromStr for Recipe {
@@ -79,3 +181,18 @@ impl FromStr for Recipe {
         Recipe::parse(src)
     }
 }
+
+impl FromStr for Amount {
+    type Err = Error;
+
+    /// Parses an amount like `1 1/2 cups` or `200g`.
+    #[cfg(feature = "diagnostics")]
+    fn from_str(src: &str) -> Result<Self> {
+        parser::parse_amount(src, 0..src.len()).map_err(|e| e.with_src(src.to_owned()))
+    }
+    /// Parses an amount like `1 1/2 cups` or `200g`.
+    #[cfg(not(feature = "diagnostics"))]
+    fn from_str(src: &str) -> Result<Self> {
+        parser::parse_amount(src, 0..src.len())
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Command line interface for working with RecipeMD files.
+
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+use miette::{miette, IntoDiagnostic, WrapErr};
+use recipemd::{
+    collection::{Collection, LoadError},
+    diff::diff,
+    format::format,
+    mealplan::{Aisles, MealPlan},
+    normalize::Canonicalizer,
+    query::Query,
+    render::TextRenderer,
+    ParseOptions, Recipe,
+};
+
+const USAGE: &str = "\
+Usage: recipemd <COMMAND>
+
+Commands:
+  show [--width <N>] [--color | --no-color] <FILE>
+      Display a recipe as formatted text
+  find <DIR> <QUERY>...
+      List all recipes in a directory that match a query,
+      e.g. `tag:vegan AND NOT ingredient:peanut AND yield>=4 servings`
+  plan [--aisles <FILE>] [--synonyms <FILE>] <FILE>
+      Create a shopping list for a meal plan, resolving the linked recipes
+      relative to the directory of the meal plan, and listing alternatives
+      like `butter or margarine` once
+  fmt [--check] <FILE>...
+      Rewrite recipes in place using a canonical layout, or only list the
+      files that are not formatted with `--check`
+  diff <OLD> <NEW>
+      List the changes between two versions of a recipe";
+
+fn main() -> miette::Result<()> {
+    let mut args = std::env::args().skip(1);
+
+    match args.next().as_deref() {
+        Some("show") => show(args),
+        Some("find") => find(args),
+        Some("plan") => plan(args),
+        Some("fmt") => fmt(args),
+        Some("diff") => diff_recipes(args),
+        Some("-h" | "--help") | None => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Some(command) => Err(miette!("unknown command `{command}`\n\n{USAGE}")),
+    }
+}
+
+fn show(mut args: impl Iterator<Item = String>) -> miette::Result<()> {
+    let stdout = std::io::stdout();
+    let mut width = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80);
+    let mut ansi = stdout.is_terminal();
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => {
+                width = args
+                    .next()
+                    .ok_or_else(|| miette!("`--width` requires a value"))?
+                    .parse()
+                    .into_diagnostic()
+                    .wrap_err("invalid value for `--width`")?
+            }
+            "--color" => ansi = true,
+            "--no-color" => ansi = false,
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(miette!("unexpected argument `{arg}`\n\n{USAGE}")),
+        }
+    }
+    let path = path.ok_or_else(|| miette!("missing <FILE> argument\n\n{USAGE}"))?;
+
+    let recipe = read_recipe(&path)?;
+    print!(
+        "{}",
+        TextRenderer::new().width(width).ansi(ansi).render(&recipe)
+    );
+
+    Ok(())
+}
+
+fn find(mut args: impl Iterator<Item = String>) -> miette::Result<()> {
+    let dir = args
+        .next()
+        .map(PathBuf::from)
+        .ok_or_else(|| miette!("missing <DIR> argument\n\n{USAGE}"))?;
+    let query = args.collect::<Vec<_>>().join(" ");
+    if query.is_empty() {
+        return Err(miette!("missing <QUERY> argument\n\n{USAGE}"));
+    }
+    let query = Query::parse(&query)?;
+
+    let collection = Collection::load(&dir)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to load recipes from {}", dir.display()))?;
+    report_load_errors(&collection, |_| false);
+
+    for entry in collection.entries() {
+        if query.matches(&entry.recipe) {
+            println!(
+                "{}\t{}",
+                dir.join(&entry.path).display(),
+                entry.recipe.title
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn plan(mut args: impl Iterator<Item = String>) -> miette::Result<()> {
+    let mut aisles = Aisles::default();
+    let mut aisles_path = None;
+    let mut canonicalizer = Canonicalizer::new();
+    let mut synonyms_path = None;
+    let mut path = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--aisles" => {
+                let path = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| miette!("`--aisles` requires a value"))?;
+                aisles = Aisles::parse(&read_file(&path)?)?;
+                aisles_path = Some(path);
+            }
+            "--synonyms" => {
+                let path = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| miette!("`--synonyms` requires a value"))?;
+                canonicalizer = Canonicalizer::parse(&read_file(&path)?)?;
+                synonyms_path = Some(path);
+            }
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(miette!("unexpected argument `{arg}`\n\n{USAGE}")),
+        }
+    }
+    let path = path.ok_or_else(|| miette!("missing <FILE> argument\n\n{USAGE}"))?;
+
+    let plan = MealPlan::parse(&read_file(&path)?)?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let options = ParseOptions::new().inline_alternatives(true);
+    let collection = Collection::load_with_options(dir, &options)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to load recipes from {}", dir.display()))?;
+    // the meal plan, aisles and synonyms are no recipes, but may be part of the collection
+    let ignored: Vec<PathBuf> = [Some(&path), aisles_path.as_ref(), synonyms_path.as_ref()]
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.canonicalize().ok())
+        .collect();
+    report_load_errors(&collection, |error_path| {
+        dir.join(error_path)
+            .canonicalize()
+            .is_ok_and(|p| ignored.contains(&p))
+    });
+
+    let list = plan.shopping_list(
+        &collection,
+        path.file_name().unwrap_or_default(),
+        &aisles,
+        &canonicalizer,
+    );
+    for error in &list.errors {
+        eprintln!("warning: {error}");
+    }
+    print!("{list}");
+
+    Ok(())
+}
+
+fn fmt(args: impl Iterator<Item = String>) -> miette::Result<()> {
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(miette!("missing <FILE> argument\n\n{USAGE}"));
+    }
+
+    let mut unformatted = 0;
+    for path in &paths {
+        let src = read_file(path)?;
+        let formatted =
+            format(&src).wrap_err_with(|| format!("failed to format {}", path.display()))?;
+        if formatted == src {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            unformatted += 1;
+        } else {
+            std::fs::write(path, formatted)
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+        }
+    }
+    if unformatted > 0 {
+        return Err(miette!("{unformatted} file(s) are not formatted"));
+    }
+
+    Ok(())
+}
+
+fn diff_recipes(mut args: impl Iterator<Item = String>) -> miette::Result<()> {
+    let old = args
+        .next()
+        .ok_or_else(|| miette!("missing <OLD> argument\n\n{USAGE}"))?;
+    let new = args
+        .next()
+        .ok_or_else(|| miette!("missing <NEW> argument\n\n{USAGE}"))?;
+    if let Some(arg) = args.next() {
+        return Err(miette!("unexpected argument `{arg}`\n\n{USAGE}"));
+    }
+
+    let old = read_recipe(Path::new(&old))?;
+    let new = read_recipe(Path::new(&new))?;
+    print!("{}", diff(&old, &new));
+
+    Ok(())
+}
+
+/// Prints warnings for all files of a collection that could not be loaded, except for those
+/// that should be ignored.
+fn report_load_errors(collection: &Collection, ignore: impl Fn(&Path) -> bool) {
+    let handler = miette::GraphicalReportHandler::new();
+
+    for error in collection.errors() {
+        if ignore(error.path()) {
+            continue;
+        }
+        let path = collection.root().join(error.path());
+        match error {
+            LoadError::Parse { source, .. } => {
+                let mut report = String::new();
+                let _ = handler.render_report(&mut report, source);
+                eprintln!("warning: skipping {}\n{report}", path.display());
+            }
+            LoadError::Io { source, .. } => {
+                eprintln!("warning: skipping {}: {source}", path.display())
+            }
+        }
+    }
+}
+
+fn read_recipe(path: &Path) -> miette::Result<Recipe> {
+    Ok(Recipe::parse(&read_file(path)?)?)
+}
+
+fn read_file(path: &Path) -> miette::Result<String> {
+    std::fs::read_to_string(path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read {}", path.display()))
+}
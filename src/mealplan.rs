@@ -0,0 +1,617 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Plan meals and create a combined shopping list.
+//!
+//! A meal plan is a markdown document with an optional title, a heading for each day and a
+//! list of linked recipes with the number of servings:
+//!
+//! ```markdown
+//! # Week 12
+//!
+//! ## Monday
+//!
+//! - *2 servings* [Pancakes](pancakes.md)
+//!
+//! ## Tuesday
+//!
+//! - *4 servings* [Pizza](pizza.md)
+//! - [Salad](salad.md)
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    collection::Collection, normalize::Canonicalizer, parser::RecipeParser, Amount, Factor,
+    Ingredient, IngredientGroup, Recipe, Result,
+};
+
+/// A list of meals, grouped by day.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MealPlan {
+    pub title: Option<String>,
+    pub days: Vec<Day>,
+}
+
+/// The meals of a single day.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Day {
+    /// The heading of the day, or `None` for meals that are listed before the first heading.
+    pub title: Option<String>,
+    pub meals: Vec<Meal>,
+}
+
+/// A recipe that should be cooked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Meal {
+    pub name: String,
+    /// The link to the recipe.
+    pub link: Option<String>,
+    /// The yield the recipe should be scaled to, see [`Recipe::scale_to`].
+    pub servings: Option<Amount>,
+}
+
+impl From<Ingredient> for Meal {
+    fn from(ingredient: Ingredient) -> Self {
+        Meal {
+            name: ingredient.name,
+            link: ingredient.link,
+            servings: ingredient.amount,
+        }
+    }
+}
+
+/// Assigns ingredients to the aisles or categories of a store.
+///
+/// Aisles are written as headings followed by a list of ingredient names:
+///
+/// ```markdown
+/// ## Produce
+///
+/// - tomatoes
+/// - lettuce
+///
+/// ## Dairy
+///
+/// - milk
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aisles {
+    aisles: Vec<(String, Vec<String>)>,
+}
+
+/// A problem that occurred while creating a [`ShoppingList`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum PlanError {
+    #[error("meal \"{0}\" doesn't link to a recipe")]
+    MissingLink(String),
+    #[error("recipe \"{link}\" of \"{name}\" could not be found")]
+    UnresolvedLink { name: String, link: String },
+    #[error("recipe \"{name}\" can't be scaled to {amount}, using the original amounts")]
+    IncompatibleYield { name: String, amount: Amount },
+    #[error("recipe \"{0}\" includes itself")]
+    Cycle(String),
+}
+
+/// A list of ingredients to buy.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShoppingList {
+    /// The items grouped by aisle, in the order of the [`Aisles`]. Items without an aisle come
+    /// last.
+    pub aisles: Vec<Aisle>,
+    /// Problems that occurred while creating the list.
+    pub errors: Vec<PlanError>,
+}
+
+/// The items of a [`ShoppingList`] that can be found in the same aisle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Aisle {
+    /// The name of the aisle, or `None` for items that don't belong to any aisle.
+    pub name: Option<String>,
+    pub items: Vec<ShoppingItem>,
+}
+
+/// An ingredient of a [`ShoppingList`] and the total amount needed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShoppingItem {
+    pub name: String,
+    /// The amounts needed by all recipes, summed up if their units can be converted.
+    pub amounts: Vec<Amount>,
+    /// The names of [alternatives](Ingredient::alternatives) that could be bought instead.
+    ///
+    /// Only the item itself is counted in the amounts, since the alternatives might need
+    /// different amounts.
+    pub alternatives: Vec<String>,
+}
+
+impl MealPlan {
+    /// Parses a meal plan from a markdown string.
+    #[cfg(feature = "diagnostics")]
+    pub fn parse(src: &str) -> Result<Self> {
+        Self::parse_inner(src).map_err(|e| e.with_src(src.to_owned()))
+    }
+    /// Parses a meal plan from a markdown string.
+    #[cfg(not(feature = "diagnostics"))]
+    pub fn parse(src: &str) -> Result<Self> {
+        Self::parse_inner(src)
+    }
+
+    fn parse_inner(src: &str) -> Result<Self> {
+        let mut parser = RecipeParser::new(src);
+        let title = parser.parse_optional_title()?;
+        let (ingredients, groups) = parser.parse_ingredient_sections()?;
+
+        let mut days = Vec::new();
+        if !ingredients.is_empty() {
+            days.push(Day {
+                title: None,
+                meals: ingredients.into_iter().map(Meal::from).collect(),
+            });
+        }
+        for group in groups {
+            let mut meals = Vec::new();
+            flatten(group.ingredients, group.ingredient_groups, &mut meals);
+            days.push(Day {
+                title: Some(group.title),
+                meals: meals.into_iter().map(Meal::from).collect(),
+            });
+        }
+
+        Ok(MealPlan { title, days })
+    }
+
+    /// Returns the meals of all days.
+    pub fn meals(&self) -> impl Iterator<Item = &Meal> {
+        self.days.iter().flat_map(|day| &day.meals)
+    }
+
+    /// Creates a shopping list containing the ingredients of all meals.
+    ///
+    /// The links of the meals are resolved relative to `path`, the path of the meal plan within
+    /// the collection. Each recipe is scaled to the servings of its meal. Ingredients that link
+    /// to another recipe of the collection are replaced by the ingredients of that recipe, if
+    /// it can be scaled to the ingredient's amount. Ingredients are combined and assigned to
+    /// aisles by their canonical name. The names of their alternatives are collected in
+    /// [`ShoppingItem::alternatives`].
+    ///
+    /// ```
+    /// # use recipemd::{collection::Collection, mealplan::{Aisles, MealPlan}, normalize::Canonicalizer};
+    /// # fn main() -> recipemd::Result<()> {
+    /// let mut collection = Collection::default();
+    /// collection.insert("pancakes.md", "# Pancakes\n\n**2 servings**\n\n---\n\n- *250 ml* milk\n- *2* eggs\n");
+    /// collection.insert("omelette.md", "# Omelette\n\n**1 serving**\n\n---\n\n- *3* eggs\n- salt\n");
+    ///
+    /// let plan = MealPlan::parse("## Monday\n\n- *4 servings* [Pancakes](pancakes.md)\n- [Omelette](omelette.md)\n")?;
+    /// let aisles = Aisles::parse("## Dairy\n\n- milk\n- eggs\n")?;
+    /// let list = plan.shopping_list(&collection, "plan.md", &aisles, &Canonicalizer::new());
+    ///
+    /// assert_eq!(
+    ///     list.to_string(),
+    ///     "## Dairy\n\n- *500 ml* milk\n- *7* eggs\n\n## Other\n\n- salt\n"
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn shopping_list(
+        &self,
+        collection: &Collection,
+        path: impl AsRef<Path>,
+        aisles: &Aisles,
+        canonicalizer: &Canonicalizer,
+    ) -> ShoppingList {
+        let mut builder = ShoppingListBuilder {
+            collection,
+            canonicalizer,
+            items: Vec::new(),
+            errors: Vec::new(),
+            stack: Vec::new(),
+        };
+
+        for meal in self.meals() {
+            let Some(link) = &meal.link else {
+                builder
+                    .errors
+                    .push(PlanError::MissingLink(meal.name.clone()));
+                continue;
+            };
+            let Some(entry) = collection.resolve_link(path.as_ref(), link) else {
+                builder.errors.push(PlanError::UnresolvedLink {
+                    name: meal.name.clone(),
+                    link: link.clone(),
+                });
+                continue;
+            };
+            let factor = match &meal.servings {
+                Some(servings) => match entry.recipe.scale_factor_for(servings) {
+                    Some(factor) => factor,
+                    None => {
+                        builder.errors.push(PlanError::IncompatibleYield {
+                            name: entry.recipe.title.clone(),
+                            amount: servings.clone(),
+                        });
+                        1.0
+                    }
+                },
+                None => 1.0,
+            };
+            builder.add_recipe(&entry.path, &entry.recipe, factor);
+        }
+
+        let mut list = ShoppingList {
+            aisles: Vec::new(),
+            errors: builder.errors,
+        };
+        let mut items = builder.items;
+        for (name, names) in &aisles.aisles {
+            let (matching, rest) = items
+                .into_iter()
+                .partition(|item| names.iter().any(|n| canonicalizer.is_same(n, &item.name)));
+            items = rest;
+            if !matching.is_empty() {
+                list.aisles.push(Aisle {
+                    name: Some(name.clone()),
+                    items: matching,
+                });
+            }
+        }
+        if !items.is_empty() {
+            list.aisles.push(Aisle { name: None, items });
+        }
+
+        list
+    }
+}
+
+impl FromStr for MealPlan {
+    type Err = crate::Error;
+
+    fn from_str(src: &str) -> Result<Self> {
+        MealPlan::parse(src)
+    }
+}
+
+impl Aisles {
+    /// Parses aisles from a markdown string.
+    #[cfg(feature = "diagnostics")]
+    pub fn parse(src: &str) -> Result<Self> {
+        Self::parse_inner(src).map_err(|e| e.with_src(src.to_owned()))
+    }
+    /// Parses aisles from a markdown string.
+    #[cfg(not(feature = "diagnostics"))]
+    pub fn parse(src: &str) -> Result<Self> {
+        Self::parse_inner(src)
+    }
+
+    fn parse_inner(src: &str) -> Result<Self> {
+        let (_, groups) = RecipeParser::new(src).parse_ingredient_sections()?;
+
+        let aisles = groups
+            .into_iter()
+            .map(|group| {
+                let mut ingredients = Vec::new();
+                flatten(group.ingredients, group.ingredient_groups, &mut ingredients);
+                let names = ingredients.into_iter().map(|i| i.name).collect();
+                (group.title, names)
+            })
+            .collect();
+
+        Ok(Aisles { aisles })
+    }
+
+    /// Returns the name of the aisle that contains an ingredient.
+    pub fn aisle_of(&self, name: &str, canonicalizer: &Canonicalizer) -> Option<&str> {
+        self.aisles
+            .iter()
+            .find(|(_, names)| names.iter().any(|n| canonicalizer.is_same(n, name)))
+            .map(|(aisle, _)| aisle.as_str())
+    }
+}
+
+impl FromStr for Aisles {
+    type Err = crate::Error;
+
+    fn from_str(src: &str) -> Result<Self> {
+        Aisles::parse(src)
+    }
+}
+
+/// Writes the shopping list as a markdown ingredient list with a heading for each aisle.
+impl std::fmt::Display for ShoppingList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, aisle) in self.aisles.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            if self.aisles.len() > 1 || aisle.name.is_some() {
+                writeln!(f, "## {}\n", aisle.name.as_deref().unwrap_or("Other"))?;
+            }
+            for item in &aisle.items {
+                match &item.amounts[..] {
+                    [] => write!(f, "- {}", item.name)?,
+                    amounts => {
+                        let amounts: Vec<String> = amounts.iter().map(format_amount).collect();
+                        write!(f, "- *{}* {}", amounts.join(" + "), item.name)?
+                    }
+                }
+                match item.alternatives.is_empty() {
+                    true => writeln!(f)?,
+                    false => writeln!(f, " (or {})", item.alternatives.join(", "))?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct ShoppingListBuilder<'c> {
+    collection: &'c Collection,
+    canonicalizer: &'c Canonicalizer,
+    items: Vec<ShoppingItem>,
+    errors: Vec<PlanError>,
+    /// The paths of the recipes that are currently being added, to detect cycles.
+    stack: Vec<PathBuf>,
+}
+
+impl ShoppingListBuilder<'_> {
+    fn add_recipe(&mut self, path: &Path, recipe: &Recipe, factor: f32) {
+        if self.stack.iter().any(|p| p == path) {
+            self.errors.push(PlanError::Cycle(recipe.title.clone()));
+            return;
+        }
+        self.stack.push(path.to_owned());
+
+        for ingredient in recipe.all_ingredients() {
+            let amount = ingredient.amount.as_ref().map(|a| a.scale(factor));
+
+            let linked = self
+                .collection
+                .resolve_ingredient(path, ingredient)
+                .zip(amount.as_ref())
+                .and_then(|(entry, amount)| Some((entry, entry.recipe.scale_factor_for(amount)?)));
+            match linked {
+                Some((entry, factor)) => self.add_recipe(&entry.path, &entry.recipe, factor),
+                None => self.add_item(&ingredient.name, amount, &ingredient.alternatives),
+            }
+        }
+
+        self.stack.pop();
+    }
+
+    fn add_item(&mut self, name: &str, amount: Option<Amount>, alternatives: &[Ingredient]) {
+        let item = match self
+            .items
+            .iter_mut()
+            .find(|item| self.canonicalizer.is_same(&item.name, name))
+        {
+            Some(item) => item,
+            None => {
+                self.items.push(ShoppingItem {
+                    name: name.to_owned(),
+                    amounts: Vec::new(),
+                    alternatives: Vec::new(),
+                });
+                self.items.last_mut().expect("an item was just pushed")
+            }
+        };
+        for alternative in alternatives {
+            let is_known = std::iter::once(&item.name)
+                .chain(&item.alternatives)
+                .any(|known| self.canonicalizer.is_same(known, &alternative.name));
+            if !is_known {
+                item.alternatives.push(alternative.name.clone());
+            }
+        }
+        let Some(amount) = amount else {
+            return;
+        };
+
+        for existing in &mut item.amounts {
+            if let Some(converted) = amount.convert_to(existing.unit.as_deref()) {
+                existing.factor = match (existing.factor, converted.factor) {
+                    (Factor::Integer(a), Factor::Integer(b)) => a
+                        .checked_add(b)
+                        .map_or_else(|| Factor::Float(a as f32 + b as f32), Factor::Integer),
+                    (a, b) => Factor::Float(f32::from(a) + f32::from(b)),
+                };
+                return;
+            }
+        }
+        item.amounts.push(amount);
+    }
+}
+
+fn flatten(
+    ingredients: Vec<Ingredient>,
+    groups: Vec<IngredientGroup>,
+    flattened: &mut Vec<Ingredient>,
+) {
+    flattened.extend(ingredients);
+    for group in groups {
+        flatten(group.ingredients, group.ingredient_groups, flattened);
+    }
+}
+
+/// Formats an amount, rounding floats to at most two decimal places.
+fn format_amount(amount: &Amount) -> String {
+    match amount.factor {
+        Factor::Float(v) => Amount {
+            factor: Factor::Float((v * 100.0).round() / 100.0),
+            unit: amount.unit.clone(),
+        }
+        .to_string(),
+        _ => amount.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::ParseOptions;
+
+    #[test]
+    fn parse_meal_plan() {
+        let plan = MealPlan::parse(
+            "# Week\n\n- [Bread](bread.md)\n\n## Monday\n\n### Lunch\n\n- *2 servings* [Soup](soup.md)\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            plan,
+            MealPlan {
+                title: Some("Week".to_owned()),
+                days: vec![
+                    Day {
+                        title: None,
+                        meals: vec![Meal {
+                            name: "Bread".to_owned(),
+                            link: Some("bread.md".to_owned()),
+                            servings: None,
+                        }],
+                    },
+                    Day {
+                        title: Some("Monday".to_owned()),
+                        meals: vec![Meal {
+                            name: "Soup".to_owned(),
+                            link: Some("soup.md".to_owned()),
+                            servings: Some(Amount {
+                                factor: Factor::Integer(2),
+                                unit: Some("servings".to_owned())
+                            }),
+                        }],
+                    },
+                ],
+            }
+        );
+        assert!(MealPlan::parse("Just some text").is_err());
+    }
+
+    #[test]
+    fn shopping_list_with_linked_recipes() {
+        let mut collection = Collection::default();
+        collection.insert(
+            "plans/week.md",
+            "# Not a recipe\n\n---\n", // the plan itself may be part of the collection
+        );
+        collection.insert(
+            "pizza.md",
+            "# Pizza\n\n**2 pizzas**\n\n---\n\n- *1 kg* [dough](basics/dough.md)\n- *400 g* tomatoes\n",
+        );
+        collection.insert(
+            "basics/dough.md",
+            "# Dough\n\n**500 g**\n\n---\n\n- *300 g* flour\n- *1 tsp* salt\n- *500 g* [itself](dough.md)\n",
+        );
+        collection.insert("salad.md", "# Salad\n\n---\n\n- *2* tomato\n");
+
+        let plan = MealPlan::parse(
+            "## Monday\n\n- *1 pizza* [Pizza](../pizza.md)\n- [Salad](../salad.md)\n\n## Tuesday\n\n- [Soup](../soup.md)\n- Leftovers\n",
+        )
+        .unwrap();
+        let list = plan.shopping_list(
+            &collection,
+            "plans/week.md",
+            &Aisles::default(),
+            &Canonicalizer::new(),
+        );
+
+        assert_eq!(
+            list.aisles[0].items,
+            vec![
+                ShoppingItem {
+                    name: "flour".to_owned(),
+                    amounts: vec![Amount {
+                        factor: Factor::Integer(300),
+                        unit: Some("g".to_owned())
+                    }],
+                    alternatives: Vec::new(),
+                },
+                ShoppingItem {
+                    name: "salt".to_owned(),
+                    amounts: vec![Amount {
+                        factor: Factor::Integer(1),
+                        unit: Some("tsp".to_owned())
+                    }],
+                    alternatives: Vec::new(),
+                },
+                ShoppingItem {
+                    name: "tomatoes".to_owned(),
+                    amounts: vec![
+                        Amount {
+                            factor: Factor::Float(200.0),
+                            unit: Some("g".to_owned())
+                        },
+                        Amount {
+                            factor: Factor::Integer(2),
+                            unit: None
+                        }
+                    ],
+                    alternatives: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            list.errors,
+            vec![
+                PlanError::Cycle("Dough".to_owned()),
+                PlanError::UnresolvedLink {
+                    name: "Soup".to_owned(),
+                    link: "../soup.md".to_owned()
+                },
+                PlanError::MissingLink("Leftovers".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn shopping_list_with_alternatives() {
+        let mut collection =
+            Collection::default().with_options(ParseOptions::new().inline_alternatives(true));
+        collection.insert(
+            "cake.md",
+            "# Cake\n\n---\n\n- *100 g* butter or margarine\n- *200 g* sugar (or honey, 150 g)\n",
+        );
+        collection.insert(
+            "cookies.md",
+            "# Cookies\n\n---\n\n- *50 g* Butter (or oil or margarine)\n- *2* eggs\n",
+        );
+
+        let plan = MealPlan::parse("- [Cake](cake.md)\n- [Cookies](cookies.md)\n").unwrap();
+        let list = plan.shopping_list(
+            &collection,
+            "plan.md",
+            &Aisles::default(),
+            &Canonicalizer::new(),
+        );
+
+        assert_eq!(
+            list.to_string(),
+            "- *150 g* butter (or margarine, oil)\n- *200 g* sugar (or honey)\n- *2* eggs\n"
+        );
+    }
+
+    #[test]
+    fn shopping_list_with_large_amounts() {
+        let mut collection = Collection::default();
+        collection.insert("a.md", "# A\n\n---\n\n- *4000000000* grains\n");
+        collection.insert("b.md", "# B\n\n---\n\n- *4000000000* grains\n");
+
+        let plan = MealPlan::parse("- [A](a.md)\n- [B](b.md)\n").unwrap();
+        let list = plan.shopping_list(
+            &collection,
+            "plan.md",
+            &Aisles::default(),
+            &Canonicalizer::new(),
+        );
+
+        assert_eq!(
+            list.aisles[0].items[0].amounts,
+            vec![Amount {
+                factor: Factor::Float(8e9),
+                unit: None
+            }]
+        );
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Cross-reference a recipe's instructions with its ingredients.
+
+use std::{cmp::Reverse, ops::Range};
+
+use lazy_regex::{regex::escape, Regex};
+
+use crate::{
+    ast::{Node, NodeKind, NodeList},
+    instructions::{steps, Step},
+    parser::RecipeParser,
+    utils::{base_name, strip_markdown, word_forms},
+    Ingredient, Recipe,
+};
+
+/// An occurrence of an ingredient's name in the instructions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mention<'r> {
+    pub ingredient: &'r Ingredient,
+    /// The byte range of the mention within the instructions.
+    pub span: Range<usize>,
+}
+
+/// The ingredients that are mentioned in a single [`Step`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepMentions<'r> {
+    pub step: Step,
+    pub mentions: Vec<Mention<'r>>,
+}
+
+/// Highlighted text in the instructions that doesn't refer to any ingredient of the recipe.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownMention {
+    pub text: String,
+    /// The byte range of the highlighted text within the instructions.
+    pub span: Range<usize>,
+}
+
+/// The result of [`Recipe::ingredient_mentions`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngredientMentions<'r> {
+    /// The mentioned ingredients of each step.
+    pub steps: Vec<StepMentions<'r>>,
+    /// Ingredients that are never mentioned in the instructions.
+    pub unused: Vec<&'r Ingredient>,
+    /// Emphasized or linked text that doesn't match any ingredient.
+    pub unknown: Vec<UnknownMention>,
+}
+
+impl Recipe {
+    /// Finds the ingredients (including those in nested groups) that are mentioned in each step
+    /// of the instructions.
+    ///
+    /// Ingredient names are matched case-insensitively as whole words, also accepting simple
+    /// singular and plural forms. For names consisting of multiple words, the last word is
+    /// matched as well if it is unique among all ingredients, e.g. "flour" for "wheat flour".
+    /// Anything after a comma or an opening parenthesis is ignored. Link destinations and inline
+    /// code are not searched.
+    ///
+    /// Authors often highlight ingredients using emphasis or links, so highlighted text that
+    /// doesn't match any ingredient is reported as an [`UnknownMention`].
+    ///
+    /// ```
+    /// # use recipemd::Recipe;
+    /// # fn main() -> recipemd::Result<()> {
+    /// let recipe = Recipe::parse(
+    ///     "# Tea\n\n---\n\n- *1* tea bag\n- *250 ml* water\n- sugar\n\n---\n\n\
+    ///      Boil the water.\n\nAdd the tea bag and *lemon*.\n",
+    /// )?;
+    /// let mentions = recipe.ingredient_mentions();
+    ///
+    /// assert_eq!(mentions.steps[0].mentions[0].ingredient.name, "water");
+    /// assert_eq!(mentions.steps[1].mentions[0].ingredient.name, "tea bag");
+    /// assert_eq!(mentions.unused[0].name, "sugar");
+    /// assert_eq!(mentions.unknown[0].text, "lemon");
+    /// # Ok(()) }
+    /// ```
+    pub fn ingredient_mentions(&self) -> IngredientMentions<'_> {
+        let src = self.instructions.as_deref().unwrap_or_default();
+        let ingredients: Vec<&Ingredient> = self.all_ingredients().collect();
+        let mut parser = RecipeParser::new(src);
+        let nodes: Vec<Node> = std::iter::from_fn(|| parser.parse_node()).collect();
+        let mentions = find_mentions(src, &nodes, &ingredients);
+
+        let steps = steps(src)
+            .into_iter()
+            .map(|step| StepMentions {
+                mentions: mentions
+                    .iter()
+                    .filter(|m| step.span.start <= m.span.start && m.span.end <= step.span.end)
+                    .cloned()
+                    .collect(),
+                step,
+            })
+            .collect();
+        let unused = ingredients
+            .iter()
+            .filter(|i| !mentions.iter().any(|m| std::ptr::eq(m.ingredient, **i)))
+            .copied()
+            .collect();
+        let unknown = find_unknown(src, &nodes, &mentions);
+
+        IngredientMentions {
+            steps,
+            unused,
+            unknown,
+        }
+    }
+}
+
+/// Finds the ingredients that are mentioned in the text of the instructions, ignoring e.g.
+/// link destinations and inline code.
+fn find_mentions<'r>(
+    src: &str,
+    nodes: &[Node],
+    ingredients: &[&'r Ingredient],
+) -> Vec<Mention<'r>> {
+    let mut text_spans = Vec::new();
+    collect_text_spans(nodes, &mut text_spans);
+    let mut mentions: Vec<Mention> = Vec::new();
+
+    for (i, term) in terms(ingredients) {
+        let Ok(regex) = Regex::new(&format!(r"(?i)\b{}\b", escape(&term))) else {
+            continue;
+        };
+        for m in regex.find_iter(src) {
+            let is_text = text_spans
+                .iter()
+                .any(|span| span.start <= m.start() && m.end() <= span.end);
+            let overlaps = mentions
+                .iter()
+                .any(|other| other.span.start < m.end() && m.start() < other.span.end);
+            if is_text && !overlaps {
+                mentions.push(Mention {
+                    ingredient: ingredients[i],
+                    span: m.range(),
+                });
+            }
+        }
+    }
+
+    mentions.sort_by_key(|m| m.span.start);
+    mentions
+}
+
+/// Collects the spans of text nodes, joining adjacent ones.
+fn collect_text_spans(nodes: &[Node], spans: &mut Vec<Range<usize>>) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Text(_) => match spans.last_mut() {
+                Some(last) if last.end == node.span.start => last.end = node.span.end,
+                _ => spans.push(node.span.clone()),
+            },
+            _ => collect_text_spans(node.children(), spans),
+        }
+    }
+}
+
+/// Returns the terms to search for, longest first, with the index of their ingredient.
+fn terms(ingredients: &[&Ingredient]) -> Vec<(usize, String)> {
+    let names: Vec<String> = ingredients
+        .iter()
+        .map(|ingredient| base_name(&ingredient.name))
+        .collect();
+    let last_word = |name: &str| name.rsplit(' ').next().unwrap_or_default().to_owned();
+
+    let mut terms = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        if name.is_empty() {
+            continue;
+        }
+        terms.extend(word_forms(name).into_iter().map(|term| (i, term)));
+
+        let last = last_word(name);
+        let is_unique = names
+            .iter()
+            .enumerate()
+            .all(|(j, other)| i == j || last_word(other) != last);
+        if last.len() < name.len() && last.chars().count() >= 3 && is_unique {
+            terms.extend(word_forms(&last).into_iter().map(|term| (i, term)));
+        }
+    }
+
+    terms.sort_by_key(|(_, term)| Reverse(term.len()));
+    terms
+}
+
+fn find_unknown(src: &str, nodes: &[Node], mentions: &[Mention]) -> Vec<UnknownMention> {
+    fn visit(src: &str, nodes: &[Node], mentions: &[Mention], unknown: &mut Vec<UnknownMention>) {
+        for node in nodes {
+            match &node.kind {
+                NodeKind::Emphasis(children)
+                | NodeKind::Strong(children)
+                | NodeKind::Link { children, .. } => {
+                    if children.is_empty() {
+                        continue;
+                    }
+                    let span = children.span();
+                    let is_known = mentions
+                        .iter()
+                        .any(|m| m.span.start < span.end && span.start < m.span.end);
+                    if !is_known {
+                        unknown.push(UnknownMention {
+                            text: strip_markdown(&src[span.clone()]),
+                            span,
+                        });
+                    }
+                }
+                NodeKind::Heading { children, .. }
+                | NodeKind::Paragraph(children)
+                | NodeKind::List(children)
+                | NodeKind::ListItem(children) => visit(src, children, mentions, unknown),
+                _ => (),
+            }
+        }
+    }
+
+    let mut unknown = Vec::new();
+    visit(src, nodes, mentions, &mut unknown);
+    unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn mentions_in_groups_and_plurals() {
+        let recipe = Recipe::parse(
+            r#"# Omelette
+
+---
+
+- *3* eggs
+- *1* tomato
+
+## Topping
+
+- *50 g* grated cheese
+- *1* onion, finely chopped
+
+---
+
+1. Whisk the Egg.
+2. Slice the tomatoes and the onion.
+3. Sprinkle with **cheese** and [chives](chives.md).
+"#,
+        )
+        .unwrap();
+        let mentions = recipe.ingredient_mentions();
+
+        let names: Vec<Vec<&str>> = mentions
+            .steps
+            .iter()
+            .map(|step| {
+                step.mentions
+                    .iter()
+                    .map(|m| m.ingredient.name.as_str())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                vec!["eggs"],
+                vec!["tomato", "onion, finely chopped"],
+                vec!["grated cheese"],
+            ]
+        );
+
+        let instructions = recipe.instructions.as_deref().unwrap();
+        let span = mentions.steps[1].mentions[0].span.clone();
+        assert_eq!(&instructions[span], "tomatoes");
+
+        assert!(mentions.unused.is_empty());
+        assert_eq!(
+            mentions.unknown,
+            vec![UnknownMention {
+                text: "chives".to_owned(),
+                span: 88..94
+            }]
+        );
+    }
+
+    #[test]
+    fn mentions_outside_of_text() {
+        let recipe = Recipe::parse(
+            "# Bread\n\n---\n\n- *500 g* flour\n- *1 tsp* salt\n\n---\n\n\
+             Mix [see flour](flour.md) with `flour` and salt.\n",
+        )
+        .unwrap();
+        let mentions = recipe.ingredient_mentions();
+
+        let found: Vec<(&str, Range<usize>)> = mentions.steps[0]
+            .mentions
+            .iter()
+            .map(|m| (m.ingredient.name.as_str(), m.span.clone()))
+            .collect();
+        assert_eq!(found, [("flour", 9..14), ("salt", 43..47)]);
+        assert!(mentions.unused.is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Convert YAML and TOML front matter into [`Value`]s.
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "front-matter")]
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{ast::MetadataKind, ErrorKind, Value};
+
+/// Parses the content of a front matter block, which must be a map at its top level.
+#[cfg(feature = "front-matter")]
+pub(crate) fn parse(
+    kind: MetadataKind,
+    content: &str,
+) -> Result<BTreeMap<String, Value>, ErrorKind> {
+    match kind {
+        MetadataKind::Yaml => parse_yaml(content),
+        MetadataKind::Toml => parse_toml(content),
+    }
+}
+
+#[cfg(not(feature = "front-matter"))]
+pub(crate) fn parse(
+    _kind: MetadataKind,
+    _content: &str,
+) -> Result<BTreeMap<String, Value>, ErrorKind> {
+    unreachable!("metadata blocks are only recognized with the `front-matter` feature")
+}
+
+#[cfg(feature = "front-matter")]
+fn parse_yaml(content: &str) -> Result<BTreeMap<String, Value>, ErrorKind> {
+    let documents = YamlLoader::load_from_str(content)
+        .map_err(|e| ErrorKind::InvalidMetadata(e.to_string()))?;
+
+    match documents.into_iter().next() {
+        None | Some(Yaml::Null) => Ok(BTreeMap::new()),
+        Some(yaml) => match yaml_to_value(yaml)? {
+            Value::Map(map) => Ok(map),
+            _ => Err(ErrorKind::InvalidMetadata(
+                "expected a map of keys and values".to_owned(),
+            )),
+        },
+    }
+}
+
+#[cfg(feature = "front-matter")]
+fn yaml_to_value(yaml: Yaml) -> Result<Value, ErrorKind> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Integer(i) => Value::Integer(i),
+        Yaml::Real(s) => s
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| ErrorKind::InvalidMetadata(format!("invalid number `{s}`")))?,
+        Yaml::String(s) => Value::String(s),
+        Yaml::Array(array) => Value::List(
+            array
+                .into_iter()
+                .map(yaml_to_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        Yaml::Hash(hash) => Value::Map(
+            hash.into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Yaml::String(s) | Yaml::Real(s) => s,
+                        Yaml::Integer(i) => i.to_string(),
+                        Yaml::Boolean(b) => b.to_string(),
+                        _ => {
+                            return Err(ErrorKind::InvalidMetadata(
+                                "keys must be strings".to_owned(),
+                            ))
+                        }
+                    };
+                    Ok((key, yaml_to_value(value)?))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        Yaml::Alias(_) | Yaml::BadValue => {
+            return Err(ErrorKind::InvalidMetadata("unsupported value".to_owned()))
+        }
+    })
+}
+
+#[cfg(feature = "front-matter")]
+fn parse_toml(content: &str) -> Result<BTreeMap<String, Value>, ErrorKind> {
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e: toml::de::Error| ErrorKind::InvalidMetadata(e.message().to_owned()))?;
+
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| (key, toml_to_value(value)))
+        .collect())
+}
+
+#[cfg(feature = "front-matter")]
+fn toml_to_value(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::Integer(i),
+        toml::Value::Float(f) => Value::Float(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(array) => Value::List(array.into_iter().map(toml_to_value).collect()),
+        toml::Value::Table(table) => Value::Map(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(all(test, feature = "front-matter"))]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::Recipe;
+
+    use super::*;
+
+    #[test]
+    fn yaml_front_matter() {
+        let recipe = Recipe::parse(
+            "---\nauthor: Jane Doe\nrating: 4.5\nprep_time: 10\nsource:\n  - book\n---\n\n# Water\n\n---\n",
+        )
+        .unwrap();
+
+        assert_eq!(recipe.title, "Water");
+        assert_eq!(
+            recipe.metadata,
+            BTreeMap::from([
+                ("author".to_owned(), Value::String("Jane Doe".to_owned())),
+                ("prep_time".to_owned(), Value::Integer(10)),
+                ("rating".to_owned(), Value::Float(4.5)),
+                (
+                    "source".to_owned(),
+                    Value::List(vec![Value::String("book".to_owned())])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn toml_front_matter() {
+        let recipe =
+            Recipe::parse("+++\nauthor = \"Jane Doe\"\nvegan = true\n+++\n\n# Water\n\n---\n")
+                .unwrap();
+
+        assert_eq!(
+            recipe.metadata,
+            BTreeMap::from([
+                ("author".to_owned(), Value::String("Jane Doe".to_owned())),
+                ("vegan".to_owned(), Value::Bool(true)),
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_front_matter() {
+        let error = Recipe::parse("---\n- not a map\n---\n\n# Water\n\n---\n").unwrap_err();
+
+        assert!(matches!(error.kind, ErrorKind::InvalidMetadata(_)));
+        assert_eq!(error.span, Some(0..19));
+    }
+}
@@ -1,14 +1,17 @@
 // Copyright (c) 2023 d-k-bo
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::extension::Extensions;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 /// A [Recipe](https://recipemd.org/specification.html#recipe) as defined by the RecipeMD specification.
 ///
 /// See the [top-level documentation](crate) for details.
-#[derive(Clone, Debug)]
-#[cfg_attr(any(test, feature = "tests"), derive(PartialEq))]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Recipe {
     pub title: String,
@@ -18,37 +21,137 @@ pub struct Recipe {
     pub ingredients: Vec<Ingredient>,
     pub ingredient_groups: Vec<IngredientGroup>,
     pub instructions: Option<String>,
+    /// Metadata from a YAML (`---`) or TOML (`+++`) front matter block before the title.
+    ///
+    /// This is not part of the RecipeMD specification and requires the `front-matter` feature.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "BTreeMap::is_empty")
+    )]
+    pub metadata: BTreeMap<String, Value>,
+    /// Data that was parsed by the [extensions](crate::extension) of the [`ParseOptions`].
+    ///
+    /// This is not part of the RecipeMD specification.
+    ///
+    /// [`ParseOptions`]: crate::ParseOptions
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub extensions: Extensions,
+}
+
+impl Recipe {
+    /// Returns all ingredients of the recipe, including those in (nested) ingredient groups.
+    pub fn all_ingredients(&self) -> impl Iterator<Item = &Ingredient> {
+        self.ingredients.iter().chain(
+            self.ingredient_groups
+                .iter()
+                .flat_map(IngredientGroup::all_ingredients),
+        )
+    }
+}
+
+/// One of the recipes of a document, see [`Recipe::parse_many`](crate::Recipe::parse_many).
+#[derive(Debug)]
+pub struct ParsedRecipe {
+    /// The byte range of the recipe in the document.
+    pub span: Range<usize>,
+    /// The recipe, or the error that occurred while parsing it. The span of the error is
+    /// relative to the document.
+    pub recipe: crate::Result<Recipe>,
 }
 
 /// An [IngredientGroup](https://recipemd.org/specification.html#ingredient-group).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[cfg_attr(any(test, feature = "tests"), derive(PartialEq))]
 pub struct IngredientGroup {
     pub title: String,
     pub ingredients: Vec<Ingredient>,
     pub ingredient_groups: Vec<IngredientGroup>,
 }
 
+impl IngredientGroup {
+    /// Returns all ingredients of the group, including those in nested ingredient groups.
+    pub fn all_ingredients(&self) -> Box<dyn Iterator<Item = &Ingredient> + '_> {
+        Box::new(
+            self.ingredients.iter().chain(
+                self.ingredient_groups
+                    .iter()
+                    .flat_map(IngredientGroup::all_ingredients),
+            ),
+        )
+    }
+}
+
 /// An [Ingredient](https://recipemd.org/specification.html#ingredient).
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[cfg_attr(any(test, feature = "tests"), derive(PartialEq))]
 pub struct Ingredient {
     pub amount: Option<Amount>,
     pub name: String,
     pub link: Option<String>,
+    /// A preparation note like "finely chopped" that was split from the name.
+    ///
+    /// This is not part of the RecipeMD specification and is only set if the recipe was parsed
+    /// with [`ParseOptions::split_notes`](crate::ParseOptions::split_notes).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub note: Option<String>,
+    /// The name as written in the recipe if a [`note`](Ingredient::note) was split from it.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub original_name: Option<String>,
+    /// Ingredients that can be used instead of this one.
+    ///
+    /// This is not part of the RecipeMD specification and is only set if the recipe was parsed
+    /// with [`SublistMode::Alternatives`](crate::SublistMode::Alternatives).
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub alternatives: Vec<Ingredient>,
+}
+
+impl Ingredient {
+    /// Returns the name as written in the recipe, including any preparation note.
+    pub fn full_name(&self) -> &str {
+        self.original_name.as_deref().unwrap_or(&self.name)
+    }
 }
 
 /// An [Amount](https://recipemd.org/specification.html#amount) used for ingredients and yields.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[cfg_attr(any(test, feature = "tests"), derive(PartialEq))]
 pub struct Amount {
     pub factor: Factor,
     pub unit: Option<String>,
 }
 
+/// A value in the front matter metadata of a [`Recipe`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(untagged))]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Returns the string if this value is a [`Value::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
 /// Represents the numerical part of an [`Amount`].
 ///
 /// Integers are serialized as integers, fractions and floats are serialized as floats.
@@ -101,9 +204,31 @@ impl<'de> Deserialize<'de> for Factor {
     }
 }
 
-#[cfg(any(test, feature = "tests"))]
 impl PartialEq for Factor {
     fn eq(&self, other: &Self) -> bool {
         f32::from(*self).eq(&f32::from(*other))
     }
 }
+
+impl std::fmt::Display for Factor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Factor::Integer(v) => write!(f, "{v}"),
+            Factor::Fraction(num, denom) if num >= denom && denom > 0 => match num % denom {
+                0 => write!(f, "{}", num / denom),
+                rem => write!(f, "{} {rem}/{denom}", num / denom),
+            },
+            Factor::Fraction(num, denom) => write!(f, "{num}/{denom}"),
+            Factor::Float(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Amount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.unit {
+            Some(unit) => write!(f, "{} {unit}", self.factor),
+            None => write!(f, "{}", self.factor),
+        }
+    }
+}
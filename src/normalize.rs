@@ -0,0 +1,281 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Normalize ingredient names so that "Tomatoes", "ripe tomatoes" and "Tomaten" can be
+//! recognized as the same ingredient.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{collection::LoadError, parser::RecipeParser, utils::base_name, Result};
+
+/// Words that describe how an ingredient is prepared or what it looks like, but not what it is.
+const PREPARATION_WORDS: &str = "
+    fresh freshly ripe large big medium small chopped finely roughly coarsely thinly diced minced
+    sliced grated shredded peeled crushed halved quartered cubed beaten sifted softened melted
+    cold organic
+    frisch frische frischer frischen reif reife reifen groß große großer großen mittelgroß
+    mittelgroße mittelgroßen klein kleine kleiner kleinen fein grob gehackt gehackte gehackter
+    gehackten gewürfelt gewürfelte gewürfelten geschnitten geschnittene gerieben geriebene
+    geriebener geriebenen geschält geschälte geschälten weich weiche weicher zerlassen zerlassene
+    zerlassener bio
+";
+
+/// Maps ingredient names to a canonical form for comparing them.
+///
+/// Names are canonicalized by
+///
+/// 1. removing markup and everything after a comma or opening parenthesis,
+/// 2. converting them to lowercase,
+/// 3. removing preparation words like "fresh", "chopped" or "gehackt",
+/// 4. converting the last word from English plural to singular using a simple heuristic and
+/// 5. looking the result up in a dictionary of synonyms.
+///
+/// The canonical form is meant for comparison only, it isn't necessarily a proper word.
+///
+/// ```
+/// # use recipemd::normalize::Canonicalizer;
+/// # fn main() -> recipemd::Result<()> {
+/// let canonicalizer = Canonicalizer::parse("## tomato\n\n- Tomaten\n- Paradeiser\n")?;
+///
+/// assert_eq!(canonicalizer.canonicalize("*Ripe* Tomatoes, halved"), "tomato");
+/// assert_eq!(canonicalizer.canonicalize("Tomaten"), "tomato");
+/// assert!(canonicalizer.is_same("cherries", "Cherry"));
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Canonicalizer {
+    pub(crate) preparation_words: HashSet<String>,
+    pub(crate) synonyms: HashMap<String, String>,
+}
+
+impl Default for Canonicalizer {
+    fn default() -> Self {
+        Self {
+            preparation_words: PREPARATION_WORDS
+                .split_whitespace()
+                .map(ToOwned::to_owned)
+                .collect(),
+            synonyms: HashMap::new(),
+        }
+    }
+}
+
+impl Canonicalizer {
+    /// Creates a canonicalizer with built-in English and German preparation words and no
+    /// synonyms.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a dictionary of synonyms from a markdown string.
+    ///
+    /// Each heading is a canonical name, followed by a list of its synonyms:
+    ///
+    /// ```markdown
+    /// ## tomato
+    ///
+    /// - Tomaten
+    /// - Paradeiser
+    ///
+    /// ## bell pepper
+    ///
+    /// - capsicum
+    /// - Paprika
+    /// ```
+    ///
+    /// Nested headings are canonical names of their own. Ingredients that are listed before the
+    /// first heading are ignored.
+    #[cfg(feature = "diagnostics")]
+    pub fn parse(src: &str) -> Result<Self> {
+        Self::parse_inner(src).map_err(|e| e.with_src(src.to_owned()))
+    }
+    /// Parses a dictionary of synonyms from a markdown string.
+    ///
+    /// See the documentation with the `diagnostics` feature enabled for details.
+    #[cfg(not(feature = "diagnostics"))]
+    pub fn parse(src: &str) -> Result<Self> {
+        Self::parse_inner(src)
+    }
+
+    fn parse_inner(src: &str) -> Result<Self> {
+        let (_, groups) = RecipeParser::new(src).parse_ingredient_sections()?;
+
+        let mut canonicalizer = Self::new();
+        let mut groups = groups;
+        while let Some(group) = groups.pop() {
+            for ingredient in &group.ingredients {
+                canonicalizer.add_synonym(&ingredient.name, &group.title);
+            }
+            groups.extend(group.ingredient_groups);
+        }
+
+        Ok(canonicalizer)
+    }
+
+    /// Loads a dictionary of synonyms from a file.
+    ///
+    /// See [`Canonicalizer::parse`] for the format.
+    pub fn load(path: impl AsRef<Path>) -> std::result::Result<Self, LoadError> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path).map_err(|source| LoadError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::parse(&src).map_err(|source| LoadError::Parse {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    /// Adds a synonym for an ingredient name.
+    pub fn add_synonym(&mut self, synonym: &str, canonical: &str) {
+        let canonical = self.canonicalize(canonical);
+        let synonym = self.normalize(synonym);
+        if synonym != canonical {
+            self.synonyms.insert(synonym, canonical);
+        }
+    }
+
+    /// Adds a word that should be ignored, like "fresh" or "chopped".
+    pub fn add_preparation_word(&mut self, word: &str) {
+        self.preparation_words.insert(word.to_lowercase());
+    }
+
+    /// Returns the canonical form of an ingredient name.
+    pub fn canonicalize(&self, name: &str) -> String {
+        let normalized = self.normalize(name);
+        match self.synonyms.get(&normalized) {
+            Some(canonical) => canonical.clone(),
+            None => normalized,
+        }
+    }
+
+    /// Returns `true` if two ingredient names have the same canonical form.
+    pub fn is_same(&self, a: &str, b: &str) -> bool {
+        self.canonicalize(a) == self.canonicalize(b)
+    }
+
+    /// Canonicalizes a name without looking up synonyms.
+    fn normalize(&self, name: &str) -> String {
+        let name = base_name(name);
+        let words: Vec<&str> = name.split_whitespace().collect();
+        let mut kept: Vec<&str> = words
+            .iter()
+            .copied()
+            .filter(|word| !self.preparation_words.contains(*word))
+            .collect();
+        if kept.is_empty() {
+            kept = words;
+        }
+
+        match kept.split_last() {
+            Some((last, rest)) => {
+                let mut words: Vec<String> = rest.iter().map(|&w| w.to_owned()).collect();
+                words.push(singularize(last));
+                words.join(" ")
+            }
+            None => String::new(),
+        }
+    }
+}
+
+impl FromStr for Canonicalizer {
+    type Err = crate::Error;
+
+    fn from_str(src: &str) -> Result<Self> {
+        Canonicalizer::parse(src)
+    }
+}
+
+/// Converts an English noun to a singular form.
+///
+/// Words ending with "ie" are spelled with "y" to match their plural form, e.g. both "cookie" and
+/// "cookies" become "cooky".
+fn singularize(word: &str) -> String {
+    let len = word.chars().count();
+    if len <= 3 {
+        return word.to_owned();
+    }
+    if let Some(stem) = word.strip_suffix("ies").filter(|_| len > 4) {
+        return format!("{stem}y");
+    }
+    if let Some(stem) = word.strip_suffix("ie") {
+        return format!("{stem}y");
+    }
+    for suffix in ["oes", "ches", "shes", "sses", "xes", "zes"] {
+        if word.ends_with(suffix) {
+            return word[..word.len() - 2].to_owned();
+        }
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if !["s", "u", "i"].iter().any(|s| stem.ends_with(s)) => stem.to_owned(),
+        _ => word.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn singular_forms() {
+        let words = [
+            "tomatoes",
+            "potato",
+            "berries",
+            "cookie",
+            "cookies",
+            "peaches",
+            "glasses",
+            "eggs",
+            "asparagus",
+            "hummus",
+            "pies",
+            "oats",
+        ];
+        assert_eq!(
+            words.map(singularize),
+            [
+                "tomato",
+                "potato",
+                "berry",
+                "cooky",
+                "cooky",
+                "peach",
+                "glass",
+                "egg",
+                "asparagus",
+                "hummus",
+                "pie",
+                "oat"
+            ]
+        );
+    }
+
+    #[test]
+    fn canonicalize_names() {
+        let mut canonicalizer =
+            Canonicalizer::parse(
+            "- ignored\n\n## Bell Pepper\n\n- Paprika\n- capsicums\n\n### Chili\n\n- Chilli Peppers\n",
+        )
+                .unwrap();
+        canonicalizer.add_preparation_word("Ripe");
+        canonicalizer.add_synonym("scallion", "spring onions");
+
+        assert_eq!(canonicalizer.canonicalize("Large capsicum"), "bell pepper");
+        assert_eq!(canonicalizer.canonicalize("frische Paprika"), "bell pepper");
+        assert_eq!(
+            canonicalizer.canonicalize("Scallions (chopped)"),
+            "spring onion"
+        );
+        assert_eq!(canonicalizer.canonicalize("chilli pepper"), "chili");
+        assert_eq!(canonicalizer.canonicalize("Chopped"), "chopped");
+        assert_eq!(canonicalizer.canonicalize("ignored"), "ignored");
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use std::sync::Arc;
+
+use crate::extension::Extension;
+
+/// Options for [`Recipe::parse_with_options`](crate::Recipe::parse_with_options).
+///
+/// The default options parse recipes strictly according to the RecipeMD specification.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub(crate) split_notes: bool,
+    pub(crate) sublists: SublistMode,
+    pub(crate) inline_alternatives: bool,
+    pub(crate) extensions: Vec<Arc<dyn Extension>>,
+    pub(crate) recipe_separator: Option<String>,
+}
+
+/// How nested lists below an ingredient are parsed, see [`ParseOptions::sublists`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SublistMode {
+    /// The nested list is part of the ingredient's name, as required by the RecipeMD
+    /// specification.
+    #[default]
+    Flatten,
+    /// The items of the nested list are joined into the ingredient's
+    /// [`note`](crate::Ingredient::note).
+    Notes,
+    /// The items of the nested list are parsed as
+    /// [`alternatives`](crate::Ingredient::alternatives) that can be used instead of the
+    /// ingredient.
+    Alternatives,
+}
+
+impl ParseOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Split preparation notes from ingredient names into [`Ingredient::note`].
+    ///
+    /// Text in parentheses and everything after the first comma is considered a note, e.g.
+    /// `onion, finely chopped` is split into the name `onion` and the note `finely chopped`.
+    /// The unsplit name is kept in [`Ingredient::original_name`].
+    ///
+    /// [`Ingredient::note`]: crate::Ingredient::note
+    /// [`Ingredient::original_name`]: crate::Ingredient::original_name
+    pub fn split_notes(mut self, split_notes: bool) -> Self {
+        self.split_notes = split_notes;
+        self
+    }
+
+    /// Choose how nested lists below an ingredient are parsed.
+    ///
+    /// ```
+    /// # use recipemd::{ParseOptions, Recipe, SublistMode};
+    /// # fn main() -> recipemd::Result<()> {
+    /// let src = "# Cake\n\n---\n\n- *100 g* butter\n  - *80 ml* vegetable oil\n";
+    ///
+    /// let recipe = Recipe::parse(src)?;
+    /// assert_eq!(recipe.ingredients[0].name, "butter\n  - *80 ml* vegetable oil");
+    ///
+    /// let options = ParseOptions::new().sublists(SublistMode::Alternatives);
+    /// let recipe = Recipe::parse_with_options(src, &options)?;
+    /// assert_eq!(recipe.ingredients[0].name, "butter");
+    /// assert_eq!(recipe.ingredients[0].alternatives[0].name, "vegetable oil");
+    /// # Ok(()) }
+    /// ```
+    pub fn sublists(mut self, mode: SublistMode) -> Self {
+        self.sublists = mode;
+        self
+    }
+
+    /// Parse alternatives written inline in an ingredient's name into
+    /// [`Ingredient::alternatives`].
+    ///
+    /// Alternatives are separated by `or` (or `oder`), e.g. `butter or margarine`, or written in
+    /// parentheses that start with `or`, e.g. `sugar (or honey, 150 g)`. An alternative may
+    /// have its own amount, either emphasized in front of the name like in the ingredient list
+    /// or after a trailing comma. The unsplit name is kept in [`Ingredient::original_name`].
+    ///
+    /// Alternatives are split before [notes](ParseOptions::split_notes), so the parentheses
+    /// of an alternative don't end up in the note.
+    ///
+    /// ```
+    /// # use recipemd::{ParseOptions, Recipe};
+    /// # fn main() -> recipemd::Result<()> {
+    /// let src = "# Cake\n\n---\n\n- *200 g* sugar (or honey, 150 g)\n- *100 g* butter or margarine\n";
+    /// let options = ParseOptions::new().inline_alternatives(true);
+    /// let recipe = Recipe::parse_with_options(src, &options)?;
+    ///
+    /// let sugar = &recipe.ingredients[0];
+    /// assert_eq!(sugar.name, "sugar");
+    /// assert_eq!(sugar.alternatives[0].name, "honey");
+    /// assert_eq!(sugar.alternatives[0].amount.as_ref().unwrap().to_string(), "150 g");
+    ///
+    /// let butter = &recipe.ingredients[1];
+    /// assert_eq!(butter.name, "butter");
+    /// assert_eq!(butter.alternatives[0].name, "margarine");
+    /// assert!(butter.alternatives[0].amount.is_none());
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Ingredient::alternatives`]: crate::Ingredient::alternatives
+    /// [`Ingredient::original_name`]: crate::Ingredient::original_name
+    pub fn inline_alternatives(mut self, inline_alternatives: bool) -> Self {
+        self.inline_alternatives = inline_alternatives;
+        self
+    }
+
+    /// Register an [`Extension`] that recognizes additional constructs and stores their data
+    /// in [`Recipe::extensions`].
+    ///
+    /// See the [`extension`](crate::extension) module for an example.
+    ///
+    /// [`Recipe::extensions`]: crate::Recipe::extensions
+    pub fn extension(mut self, extension: impl Extension + 'static) -> Self {
+        self.extensions.push(Arc::new(extension));
+        self
+    }
+
+    /// Split documents with multiple recipes at lines that consist of `separator` instead of
+    /// at first level headings, see [`Recipe::parse_many`].
+    ///
+    /// ```
+    /// # use recipemd::{ParseOptions, Recipe};
+    /// let src = "# Tea\n\n---\n\n<!-- recipe -->\n\n# Coffee\n\n---\n";
+    /// let options = ParseOptions::new().recipe_separator("<!-- recipe -->");
+    /// let recipes = Recipe::parse_many_with_options(src, &options);
+    ///
+    /// assert_eq!(recipes.len(), 2);
+    /// assert_eq!(&src[recipes[1].span.clone()], "\n# Coffee\n\n---\n");
+    /// ```
+    ///
+    /// [`Recipe::parse_many`]: crate::Recipe::parse_many
+    pub fn recipe_separator(mut self, separator: impl Into<String>) -> Self {
+        self.recipe_separator = Some(separator.into());
+        self
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Find out which recipes can be cooked with the ingredients at hand.
+
+use std::{cmp::Reverse, str::FromStr};
+
+use crate::{
+    collection::{Collection, Entry},
+    normalize::Canonicalizer,
+    parser::RecipeParser,
+    Amount, Factor, Ingredient, Recipe, Result,
+};
+
+/// An inventory of available ingredients.
+///
+/// A pantry is written as a RecipeMD ingredient list, optionally split into groups by headings:
+///
+/// ```
+/// # use recipemd::{pantry::{Availability, Pantry}, Recipe};
+/// # fn main() -> recipemd::Result<()> {
+/// let pantry = Pantry::parse("## Cupboard\n\n- *1 kg* flour\n- sugar\n\n## Fridge\n\n- *2* eggs\n")?;
+/// let recipe = Recipe::parse("# Cake\n\n---\n\n- *250 g* flour\n- *3* eggs\n- *100 g* sugar\n- butter\n")?;
+///
+/// let report = pantry.check(&recipe);
+/// assert!(matches!(report.ingredients[0].1, Availability::Sufficient));
+/// assert!(matches!(report.ingredients[1].1, Availability::Insufficient { .. }));
+/// assert!(matches!(report.ingredients[2].1, Availability::Unchecked));
+/// assert!(matches!(report.ingredients[3].1, Availability::Missing));
+/// assert_eq!(report.available(), 3);
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Pantry {
+    ingredients: Vec<Ingredient>,
+    canonicalizer: Canonicalizer,
+}
+
+/// Whether an ingredient of a recipe is available in a [`Pantry`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Availability {
+    /// The pantry contains enough of the ingredient.
+    Sufficient,
+    /// The pantry contains the ingredient, but not enough of it.
+    Insufficient {
+        /// The amount that is missing, in the unit used by the recipe.
+        missing: Amount,
+    },
+    /// The pantry contains the ingredient, but the amounts can't be compared because either of
+    /// them is missing or their units can't be converted.
+    Unchecked,
+    /// The pantry doesn't contain the ingredient, or not enough of it, but one of its
+    /// [`alternatives`](Ingredient::alternatives).
+    Substitute {
+        /// The index of the alternative in [`Ingredient::alternatives`].
+        alternative: usize,
+        /// The availability of the alternative, either [`Availability::Sufficient`] or
+        /// [`Availability::Unchecked`].
+        availability: Box<Availability>,
+    },
+    /// The pantry doesn't contain the ingredient.
+    Missing,
+}
+
+impl Availability {
+    /// Returns `true` if the ingredient is in the pantry, regardless of its amount.
+    pub fn is_available(&self) -> bool {
+        !matches!(self, Availability::Missing)
+    }
+}
+
+/// The availability of each ingredient of a recipe.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PantryReport<'r> {
+    pub ingredients: Vec<(&'r Ingredient, Availability)>,
+}
+
+impl<'r> PantryReport<'r> {
+    /// Returns the number of ingredients that are in the pantry.
+    pub fn available(&self) -> usize {
+        self.ingredients
+            .iter()
+            .filter(|(_, availability)| availability.is_available())
+            .count()
+    }
+
+    /// Returns the ingredients that are not in the pantry at all.
+    pub fn missing(&self) -> impl Iterator<Item = &'r Ingredient> + '_ {
+        self.ingredients
+            .iter()
+            .filter(|(_, availability)| !availability.is_available())
+            .map(|(ingredient, _)| *ingredient)
+    }
+
+    /// Returns the ingredients that are in the pantry, but not in a sufficient amount, together
+    /// with the missing amount.
+    pub fn insufficient(&self) -> impl Iterator<Item = (&'r Ingredient, &Amount)> + '_ {
+        self.ingredients
+            .iter()
+            .filter_map(|(ingredient, availability)| match availability {
+                Availability::Insufficient { missing } => Some((*ingredient, missing)),
+                _ => None,
+            })
+    }
+
+    /// Returns the ingredients that can be replaced by one of their alternatives, together with
+    /// that alternative.
+    pub fn substituted(&self) -> impl Iterator<Item = (&'r Ingredient, &'r Ingredient)> + '_ {
+        self.ingredients
+            .iter()
+            .filter_map(|(ingredient, availability)| match availability {
+                Availability::Substitute { alternative, .. } => {
+                    Some((*ingredient, &ingredient.alternatives[*alternative]))
+                }
+                _ => None,
+            })
+    }
+
+    /// Returns the fraction of ingredients that are in the pantry, between 0 and 1.
+    ///
+    /// Recipes without ingredients have a score of 1.
+    pub fn score(&self) -> f32 {
+        match self.ingredients.len() {
+            0 => 1.0,
+            n => self.available() as f32 / n as f32,
+        }
+    }
+}
+
+impl Pantry {
+    /// Creates a pantry containing the given ingredients.
+    pub fn new(ingredients: Vec<Ingredient>) -> Self {
+        Self {
+            ingredients,
+            canonicalizer: Canonicalizer::default(),
+        }
+    }
+
+    /// Sets the canonicalizer that is used for comparing ingredient names.
+    pub fn with_canonicalizer(mut self, canonicalizer: Canonicalizer) -> Self {
+        self.canonicalizer = canonicalizer;
+        self
+    }
+
+    /// Parses a pantry from a markdown ingredient list.
+    #[cfg(feature = "diagnostics")]
+    pub fn parse(src: &str) -> Result<Self> {
+        RecipeParser::new(src)
+            .parse_ingredient_list()
+            .map(Self::new)
+            .map_err(|e| e.with_src(src.to_owned()))
+    }
+    /// Parses a pantry from a markdown ingredient list.
+    #[cfg(not(feature = "diagnostics"))]
+    pub fn parse(src: &str) -> Result<Self> {
+        RecipeParser::new(src)
+            .parse_ingredient_list()
+            .map(Self::new)
+    }
+
+    /// Returns all ingredients in the pantry.
+    pub fn ingredients(&self) -> &[Ingredient] {
+        &self.ingredients
+    }
+
+    /// Returns all ingredients in the pantry with the same name as the given ingredient.
+    ///
+    /// Names are compared by their canonical form, see [`Canonicalizer`].
+    pub fn find<'p>(&'p self, ingredient: &Ingredient) -> impl Iterator<Item = &'p Ingredient> {
+        let name = self.canonicalizer.canonicalize(&ingredient.name);
+        self.ingredients
+            .iter()
+            .filter(move |other| self.canonicalizer.canonicalize(&other.name) == name)
+    }
+
+    /// Checks which ingredients of a recipe are available.
+    ///
+    /// Amounts of the same ingredient in the pantry are added up and converted to the unit used
+    /// by the recipe if possible.
+    ///
+    /// If there isn't enough of an ingredient, its [`alternatives`](Ingredient::alternatives)
+    /// are checked as well. An alternative is used if the pantry contains enough of it, or if
+    /// its amount can't be checked while the ingredient itself is missing.
+    pub fn check<'r>(&self, recipe: &'r Recipe) -> PantryReport<'r> {
+        PantryReport {
+            ingredients: recipe
+                .all_ingredients()
+                .map(|ingredient| (ingredient, self.availability(ingredient)))
+                .collect(),
+        }
+    }
+
+    /// Checks all recipes of a collection and ranks them by the fraction of available
+    /// ingredients, best first.
+    pub fn rank<'c>(&self, collection: &'c Collection) -> Vec<(&'c Entry, PantryReport<'c>)> {
+        let mut ranking: Vec<(&Entry, PantryReport)> = collection
+            .entries()
+            .iter()
+            .map(|entry| (entry, self.check(&entry.recipe)))
+            .collect();
+        ranking.sort_by(|(a, a_report), (b, b_report)| {
+            b_report
+                .score()
+                .total_cmp(&a_report.score())
+                .then_with(|| {
+                    let insufficient = |report: &PantryReport| report.insufficient().count();
+                    insufficient(a_report).cmp(&insufficient(b_report))
+                })
+                .then_with(|| {
+                    Reverse(a_report.ingredients.len()).cmp(&Reverse(b_report.ingredients.len()))
+                })
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        ranking
+    }
+
+    fn availability(&self, ingredient: &Ingredient) -> Availability {
+        let availability = self.stock_availability(ingredient);
+        if matches!(availability, Availability::Sufficient) {
+            return availability;
+        }
+
+        let alternatives: Vec<Availability> = ingredient
+            .alternatives
+            .iter()
+            .map(|alternative| self.stock_availability(alternative))
+            .collect();
+        let substitute = |wanted: fn(&Availability) -> bool| {
+            let alternative = alternatives.iter().position(wanted)?;
+            Some(Availability::Substitute {
+                alternative,
+                availability: Box::new(alternatives[alternative].clone()),
+            })
+        };
+        if let Some(substitute) = substitute(|a| matches!(a, Availability::Sufficient)) {
+            return substitute;
+        }
+        if matches!(availability, Availability::Missing) {
+            if let Some(substitute) = substitute(|a| matches!(a, Availability::Unchecked)) {
+                return substitute;
+            }
+        }
+        availability
+    }
+
+    /// Checks the availability of an ingredient, ignoring its alternatives.
+    fn stock_availability(&self, ingredient: &Ingredient) -> Availability {
+        let stock: Vec<&Ingredient> = self.find(ingredient).collect();
+        if stock.is_empty() {
+            return Availability::Missing;
+        }
+        let Some(needed) = &ingredient.amount else {
+            return Availability::Sufficient;
+        };
+
+        let available: Vec<f32> = stock
+            .iter()
+            .filter_map(|other| other.amount.as_ref()?.convert_to(needed.unit.as_deref()))
+            .map(|amount| f32::from(amount.factor))
+            .collect();
+        if available.is_empty() {
+            return Availability::Unchecked;
+        }
+
+        let missing = f32::from(needed.factor) - available.iter().sum::<f32>();
+        match missing > 0.0 {
+            true => Availability::Insufficient {
+                missing: Amount {
+                    factor: Factor::Float(missing),
+                    unit: needed.unit.clone(),
+                },
+            },
+            false => Availability::Sufficient,
+        }
+    }
+}
+
+impl FromStr for Pantry {
+    type Err = crate::Error;
+
+    fn from_str(src: &str) -> Result<Self> {
+        Pantry::parse(src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn check_amounts() {
+        let pantry = Pantry::parse(
+            "- *1 lb* Flour\n- *200 g* flour, whole wheat\n- *0.5 l* milk\n- *3 cloves* Knoblauch\n- tomatoes\n",
+        )
+        .unwrap()
+        .with_canonicalizer(Canonicalizer::parse("## garlic\n\n- Knoblauch\n").unwrap());
+        let recipe = Recipe::parse(
+            "# Test\n\n---\n\n- *600 g* flour\n- *1 cup* milk\n- *4 cloves* garlic\n- *2* ripe tomato\n- salt\n",
+        )
+        .unwrap();
+        let report = pantry.check(&recipe);
+
+        let availability: Vec<Availability> =
+            report.ingredients.iter().map(|(_, a)| a.clone()).collect();
+        assert_eq!(
+            availability,
+            vec![
+                Availability::Sufficient,
+                Availability::Sufficient,
+                Availability::Insufficient {
+                    missing: Amount {
+                        factor: Factor::Float(1.0),
+                        unit: Some("cloves".to_owned())
+                    }
+                },
+                Availability::Unchecked,
+                Availability::Missing,
+            ]
+        );
+        assert_eq!(
+            report
+                .missing()
+                .map(|i| i.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["salt"]
+        );
+        assert_eq!(report.score(), 0.8);
+    }
+
+    #[test]
+    fn rank_collection() {
+        let mut collection = Collection::default();
+        collection.insert(
+            "pancakes.md",
+            "# Pancakes\n\n---\n\n- *2* eggs\n- *250 ml* milk\n- flour\n",
+        );
+        collection.insert("omelette.md", "# Omelette\n\n---\n\n- *3* eggs\n- salt\n");
+        collection.insert("toast.md", "# Toast\n\n---\n\n- bread\n");
+
+        let pantry: Pantry = "- *2* eggs\n- *1 l* milk\n- flour\n- salt".parse().unwrap();
+        let titles: Vec<&str> = pantry
+            .rank(&collection)
+            .into_iter()
+            .map(|(entry, _)| entry.recipe.title.as_str())
+            .collect();
+
+        assert_eq!(titles, vec!["Pancakes", "Omelette", "Toast"]);
+    }
+
+    #[test]
+    fn check_alternatives() {
+        let pantry = Pantry::parse("- *100 g* butter\n- *1 l* oil\n- margarine\n").unwrap();
+        let recipe = Recipe::parse_with_options(
+            "# Test\n\n---\n\n- *200 g* butter (or *150 ml* oil)\n- ghee or *50 g* margarine\n- *50 g* butter or lard\n- lard or suet\n",
+            &crate::ParseOptions::new().inline_alternatives(true),
+        )
+        .unwrap();
+        let report = pantry.check(&recipe);
+
+        let availability: Vec<Availability> =
+            report.ingredients.iter().map(|(_, a)| a.clone()).collect();
+        assert_eq!(
+            availability,
+            vec![
+                Availability::Substitute {
+                    alternative: 0,
+                    availability: Box::new(Availability::Sufficient)
+                },
+                Availability::Substitute {
+                    alternative: 0,
+                    availability: Box::new(Availability::Unchecked)
+                },
+                Availability::Sufficient,
+                Availability::Missing,
+            ]
+        );
+        assert_eq!(
+            report
+                .substituted()
+                .map(|(i, a)| (i.name.as_str(), a.name.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("butter", "oil"), ("ghee", "margarine")]
+        );
+        assert_eq!(report.score(), 0.75);
+    }
+}
@@ -1,52 +1,69 @@
 // Copyright (c) 2023 d-k-bo
 // SPDX-License-Identifier: LGPL-3.0-or-later
 
-use std::{iter::Peekable, ops::Range};
+use std::{borrow::Cow, collections::BTreeMap, iter::Peekable, ops::Range};
 
 use lazy_regex::regex;
-use pulldown_cmark::{CowStr, HeadingLevel, OffsetIter, Parser};
+use pulldown_cmark::{Event, HeadingLevel, OffsetIter, Options, Parser, Tag};
 
 use crate::{
     ast::{Node, NodeKind, NodeList},
+    document::{IngredientGroupSpans, IngredientSpans, SourceMap},
+    extension::{Extension, Extensions},
+    metadata,
+    options::SublistMode,
     utils::{decode_unicode_fraction, escape_url, FromStrParseExpect, TrimNewlines},
-    Amount, Error, ErrorKind, Factor, Ingredient, IngredientGroup, Recipe, Result,
+    Amount, Error, ErrorKind, Factor, Ingredient, IngredientGroup, ParseOptions, Recipe, Result,
+    Value,
 };
 
 pub(crate) struct RecipeParser<'s> {
     pub(crate) parser: Peekable<OffsetIter<'s>>,
     pub(crate) src: &'s str,
     pub(crate) pos: usize,
+    pub(crate) options: ParseOptions,
+    /// The spans of the parts that were parsed so far.
+    pub(crate) source_map: SourceMap,
+    /// The data of the extensions that was parsed so far.
+    pub(crate) extensions: Extensions,
 }
 
 impl<'s> RecipeParser<'s> {
     pub(crate) fn new(src: &'s str) -> Self {
-        let parser = Parser::new(src).into_offset_iter().peekable();
+        let mut options = Options::empty();
+        if cfg!(feature = "front-matter") {
+            options.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+            options.insert(Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS);
+        }
+        let parser = Parser::new_ext(src, options).into_offset_iter().peekable();
 
         Self {
             parser,
             src,
             pos: 0,
+            options: ParseOptions::default(),
+            source_map: SourceMap::default(),
+            extensions: Extensions::new(),
         }
     }
+
+    pub(crate) fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
 }
 
 impl RecipeParser<'_> {
     pub(crate) fn parse_recipe(&mut self) -> Result<Recipe> {
+        let metadata = self.parse_metadata()?;
         let title = self.parse_title()?;
         let DescriptionTagsYields {
             description,
             tags,
             yields,
         } = self.parse_description_tags_yields()?;
-
-        let (ingredients, ingredient_groups) = self.parse_all_ingredients()?;
-
-        let instructions = (self.pos < self.src.len())
-            .then(|| self.src[self.pos..].trim_newlines())
-            .and_then(|s| match s.is_empty() {
-                true => None,
-                false => Some(s.to_owned()),
-            });
+        let (ingredients, ingredient_groups) = self.parse_ingredients()?;
+        let instructions = self.parse_instructions();
 
         Ok(Recipe {
             title,
@@ -56,34 +73,134 @@ impl RecipeParser<'_> {
             ingredients,
             ingredient_groups,
             instructions,
+            metadata,
+            extensions: std::mem::take(&mut self.extensions),
         })
     }
+
+    /// Parses the ingredients and ingredient groups up to the horizontal line before the
+    /// instructions, applying the options.
+    pub(crate) fn parse_ingredients(&mut self) -> Result<(Vec<Ingredient>, Vec<IngredientGroup>)> {
+        let (mut ingredients, mut ingredient_groups) = self.parse_all_ingredients()?;
+        if self.options.inline_alternatives {
+            inline_alternatives(&mut ingredients, &mut ingredient_groups);
+        }
+        if self.options.split_notes {
+            split_notes(&mut ingredients, &mut ingredient_groups);
+        }
+        Ok((ingredients, ingredient_groups))
+    }
+
+    /// Returns the rest of the source as instructions.
+    pub(crate) fn parse_instructions(&mut self) -> Option<String> {
+        if self.pos < self.src.len() {
+            let span = trim_span(self.src, self.pos..self.src.len());
+            if !span.is_empty() {
+                self.source_map.instructions = Some(span);
+            }
+        }
+        (self.pos < self.src.len())
+            .then(|| self.src[self.pos..].trim_newlines())
+            .and_then(|s| match s.is_empty() {
+                true => None,
+                false => Some(s.to_owned()),
+            })
+    }
+}
+
+impl RecipeParser<'_> {
+    /// Parses a standalone list of ingredients that may be split into groups by headings.
+    pub(crate) fn parse_ingredient_list(&mut self) -> Result<Vec<Ingredient>> {
+        let (mut ingredients, ingredient_groups) = self.parse_ingredient_sections()?;
+
+        fn flatten(groups: Vec<IngredientGroup>, ingredients: &mut Vec<Ingredient>) {
+            for group in groups {
+                ingredients.extend(group.ingredients);
+                flatten(group.ingredient_groups, ingredients);
+            }
+        }
+        flatten(ingredient_groups, &mut ingredients);
+
+        Ok(ingredients)
+    }
+
+    /// Parses a document that only consists of ingredient lists and headings.
+    pub(crate) fn parse_ingredient_sections(
+        &mut self,
+    ) -> Result<(Vec<Ingredient>, Vec<IngredientGroup>)> {
+        let sections = self.parse_all_ingredients().map_err(|e| match e.kind {
+            ErrorKind::ExpectedHorizontalLine => {
+                Error::new(ErrorKind::ExpectedIngredientList, e.span)
+            }
+            _ => e,
+        })?;
+        if let Some(node) = self.parse_node() {
+            return Err(Error::new(ErrorKind::ExpectedIngredientList, node.span));
+        }
+
+        Ok(sections)
+    }
+
+    /// Parses a first level heading if the document starts with one.
+    pub(crate) fn parse_optional_title(&mut self) -> Result<Option<String>> {
+        match self.parser.peek() {
+            Some((
+                Event::Start(Tag::Heading {
+                    level: HeadingLevel::H1,
+                    ..
+                }),
+                _,
+            )) => self.parse_title().map(Some),
+            _ => Ok(None),
+        }
+    }
 }
 
-struct DescriptionTagsYields {
-    description: Option<String>,
-    tags: Vec<String>,
-    yields: Vec<Amount>,
+pub(crate) struct DescriptionTagsYields {
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub yields: Vec<Amount>,
 }
 
 impl RecipeParser<'_> {
-    fn parse_title(&mut self) -> Result<String> {
+    pub(crate) fn parse_metadata(&mut self) -> Result<BTreeMap<String, Value>> {
+        let Some((Event::Start(Tag::MetadataBlock(_)), _)) = self.parser.peek() else {
+            return Ok(BTreeMap::new());
+        };
+        let Some(Node {
+            kind: NodeKind::MetadataBlock { kind, children },
+            span,
+        }) = self.parse_node()
+        else {
+            unreachable!("peeked a metadata block")
+        };
+        self.source_map.front_matter = Some(trim_span(self.src, span.clone()));
+        let content = match children.is_empty() {
+            true => "",
+            false => &self.src[children.span()],
+        };
+
+        metadata::parse(kind, content).map_err(|e| Error::new(e, span))
+    }
+
+    pub(crate) fn parse_title(&mut self) -> Result<String> {
         match self.parse_node() {
             Some(Node {
-                kind:
-                    NodeKind::Heading {
-                        level: HeadingLevel::H1,
-                        children,
-                    },
-                ..
-            }) => Ok(self.src[children.span()].to_owned()),
+                kind: NodeKind::Heading { level: 1, children },
+                span: heading,
+            }) => {
+                let span = heading_text_span(self.src, &heading, &children);
+                self.source_map.title = Some(span.clone());
+                self.source_map.title_heading = Some(trim_span(self.src, heading));
+                Ok(self.src[span].to_owned())
+            }
             Some(Node { span, .. }) => Err(Error::new(ErrorKind::ExpectedTitle, span)),
             None => Err(Error::new(ErrorKind::ExpectedTitle, None)),
         }
     }
 
-    fn parse_description_tags_yields(&mut self) -> Result<DescriptionTagsYields> {
-        let description_start = self.pos;
+    pub(crate) fn parse_description_tags_yields(&mut self) -> Result<DescriptionTagsYields> {
+        let mut description_start = self.pos;
 
         enum DescriptionState {
             None,
@@ -100,8 +217,30 @@ impl RecipeParser<'_> {
                 return Err(Error::new(ErrorKind::ExpectedHorizontalLine, None));
             };
 
+            if !matches!(node.kind, NodeKind::HorizontalLine)
+                && apply_extensions(
+                    &self.options,
+                    &mut self.extensions,
+                    &node.span,
+                    |extension, data| extension.parse_body_block(self.src, &node, data),
+                )?
+            {
+                // consumed blocks end the description like tags and yields
+                match description_state {
+                    DescriptionState::None => description_start = node.span.end,
+                    DescriptionState::Started { end } => {
+                        description_state = DescriptionState::Final { end }
+                    }
+                    DescriptionState::Final { .. } => (),
+                }
+                continue;
+            }
+
             match node.kind {
                 NodeKind::HorizontalLine => {
+                    self.source_map
+                        .dividers
+                        .push(trim_span(self.src, node.span.clone()));
                     if let DescriptionState::Started { .. } = description_state {
                         description_state = DescriptionState::Final {
                             end: node.span.start,
@@ -125,15 +264,27 @@ impl RecipeParser<'_> {
                             if let DescriptionState::Started { end } = description_state {
                                 description_state = DescriptionState::Final { end }
                             }
-                            let src = &self.src[children.span()];
+                            self.source_map.tags_block = Some(span.clone());
+                            let span = children.span();
 
-                            tags = Some(
+                            let spans: Vec<Range<usize>> =
                                 // https://regex101.com/r/1MmcHz/1
                                 regex!(r"(?:[^,\d]*(?:\d+(?:,\d+)*)*[^,\d]*)*")
-                                    .find_iter(src)
-                                    .map(|m| m.as_str().trim().to_owned())
+                                    .find_iter(&self.src[span.clone()])
+                                    .map(|m| {
+                                        trim_span(
+                                            self.src,
+                                            span.start + m.start()..span.start + m.end(),
+                                        )
+                                    })
+                                    .collect();
+                            tags = Some(
+                                spans
+                                    .iter()
+                                    .map(|span| self.src[span.clone()].to_owned())
                                     .collect(),
                             );
+                            self.source_map.tags = spans;
                         }
                         // does the paragraph contain yields?
                         [Node {
@@ -149,20 +300,25 @@ impl RecipeParser<'_> {
                             if let DescriptionState::Started { end } = description_state {
                                 description_state = DescriptionState::Final { end }
                             }
+                            self.source_map.yields_block = Some(span.clone());
                             let span = children.span();
 
-                            yields = Some(
+                            let spans: Vec<Range<usize>> =
                                 // https://regex101.com/r/1MmcHz/1
                                 regex!(r"(?:[^,\d]*(?:\d+(?:,\d+)*)*[^,\d]*)*")
                                     .find_iter(&self.src[span.clone()])
-                                    .map(|m| {
-                                        parse_amount(
-                                            self.src,
-                                            span.start + m.start()..span.start + m.end(),
-                                        )
-                                    })
+                                    .map(|m| span.start + m.start()..span.start + m.end())
+                                    .collect();
+                            yields = Some(
+                                spans
+                                    .iter()
+                                    .map(|span| parse_amount(self.src, span.clone()))
                                     .collect::<Result<Vec<Amount>>>()?,
                             );
+                            self.source_map.yields = spans
+                                .into_iter()
+                                .map(|span| trim_span(self.src, span))
+                                .collect();
                         }
                         // the paragraph is part of the description
                         children => {
@@ -197,15 +353,21 @@ impl RecipeParser<'_> {
             }
         }
 
-        let description = match description_state {
+        let description_span = match description_state {
             DescriptionState::None => None,
-            DescriptionState::Started { .. } => Some(self.src[description_start..].trim_newlines()),
-            DescriptionState::Final { end } => {
-                Some(self.src[description_start..end].trim_newlines())
+            DescriptionState::Started { .. } => Some(description_start..self.src.len()),
+            DescriptionState::Final { end } => Some(description_start..end),
+        };
+        if let Some(span) = &description_span {
+            let span = trim_span(self.src, span.clone());
+            if !span.is_empty() {
+                self.source_map.description = Some(span);
             }
         }
-        .and_then(|d| (!d.is_empty()).then_some(d))
-        .map(ToOwned::to_owned);
+        let description = description_span
+            .map(|span| self.src[span].trim_newlines())
+            .and_then(|d| (!d.is_empty()).then_some(d))
+            .map(ToOwned::to_owned);
 
         Ok(DescriptionTagsYields {
             description,
@@ -215,38 +377,170 @@ impl RecipeParser<'_> {
     }
 
     fn parse_all_ingredients(&mut self) -> Result<(Vec<Ingredient>, Vec<IngredientGroup>)> {
-        let mut ingredients = Vec::new();
-        let mut ingredient_groups = Vec::new();
-
         let src = self.src;
+        let options = self.options.clone();
+        // keep the spans of the ingredients that were parsed before an error
+        let mut source_map = std::mem::take(&mut self.source_map);
+        let mut extensions = std::mem::take(&mut self.extensions);
         let mut nodes = std::iter::from_fn(|| self.parse_node()).peekable();
 
-        while let Some(node) = nodes.next() {
-            match node.kind {
-                NodeKind::Heading { children, level } => {
-                    ingredient_groups.push(parse_ingredient_group(
-                        src,
-                        src[children.span()].to_owned(),
-                        level,
-                        &mut nodes,
-                    )?);
+        let result =
+            parse_ingredient_nodes(src, &options, &mut source_map, &mut extensions, &mut nodes);
+        drop(nodes);
+        self.source_map = source_map;
+        self.extensions = extensions;
+        result
+    }
+}
+
+fn parse_ingredient_nodes<'s>(
+    src: &str,
+    options: &ParseOptions,
+    source_map: &mut SourceMap,
+    extensions: &mut Extensions,
+    nodes: &mut Peekable<impl Iterator<Item = Node<'s>>>,
+) -> Result<(Vec<Ingredient>, Vec<IngredientGroup>)> {
+    let mut ingredients = Vec::new();
+    let mut ingredient_groups = Vec::new();
+
+    while let Some(node) = nodes.next() {
+        match node.kind {
+            NodeKind::Heading { children, level } => {
+                let spans = GroupSpansStart::new(source_map);
+                let group = parse_ingredient_group(
+                    src,
+                    options,
+                    source_map,
+                    extensions,
+                    trim_span(src, node.span.clone()),
+                    heading_text_span(src, &node.span, &children),
+                    level,
+                    nodes,
+                )?;
+                if !spans.consumed_by_extensions(src, options, source_map, extensions, &group)? {
+                    ingredient_groups.push(group);
                 }
-                NodeKind::List(items) => {
-                    ingredients.reserve(items.len());
-                    for item in items {
-                        ingredients.push(parse_ingredient(src, &item.flatten_paragraphs())?);
-                    }
+            }
+            NodeKind::List(items) => {
+                ingredients.reserve(items.len());
+                for item in items {
+                    ingredients.push(parse_list_item(src, options, &item)?);
+                    source_map.ingredients.push(ingredient_spans(src, &item));
+                }
+            }
+            NodeKind::HorizontalLine => {
+                source_map.dividers.push(trim_span(src, node.span));
+                break;
+            }
+            _ => {
+                if !apply_extensions(options, extensions, &node.span, |extension, data| {
+                    extension.parse_ingredients_block(src, &node, data)
+                })? {
+                    return Err(Error::new(ErrorKind::ExpectedHorizontalLine, node.span));
                 }
-                NodeKind::HorizontalLine => break,
-                _ => return Err(Error::new(ErrorKind::ExpectedHorizontalLine, node.span)),
             }
         }
+    }
 
-        Ok((ingredients, ingredient_groups))
+    Ok((ingredients, ingredient_groups))
+}
+
+/// Offers a block to the extensions and returns `true` if one of them consumed it.
+fn apply_extensions(
+    options: &ParseOptions,
+    extensions: &mut Extensions,
+    span: &Range<usize>,
+    mut hook: impl FnMut(&dyn Extension, &mut Extensions) -> std::result::Result<bool, String>,
+) -> Result<bool> {
+    for extension in &options.extensions {
+        if hook(extension.as_ref(), extensions)
+            .map_err(|e| Error::new(ErrorKind::InvalidExtensionData(e), span.clone()))?
+        {
+            return Ok(true);
+        }
     }
+    Ok(false)
+}
+
+/// The lengths of the source map before an ingredient group was parsed.
+struct GroupSpansStart {
+    groups: usize,
+    ingredients: usize,
 }
 
-fn parse_amount(src: &str, span: Range<usize>) -> Result<Amount> {
+impl GroupSpansStart {
+    fn new(source_map: &SourceMap) -> Self {
+        Self {
+            groups: source_map.ingredient_groups.len(),
+            ingredients: source_map.ingredients.len(),
+        }
+    }
+
+    /// Offers a parsed ingredient group to the extensions and removes its spans if it was
+    /// consumed.
+    fn consumed_by_extensions(
+        self,
+        src: &str,
+        options: &ParseOptions,
+        source_map: &mut SourceMap,
+        extensions: &mut Extensions,
+        group: &IngredientGroup,
+    ) -> Result<bool> {
+        let span = source_map.ingredient_groups[self.groups].heading.clone();
+        let consumed = apply_extensions(options, extensions, &span, |extension, data| {
+            extension.parse_ingredient_group(src, group, data)
+        })?;
+        if consumed {
+            source_map.ingredient_groups.truncate(self.groups);
+            source_map.ingredients.truncate(self.ingredients);
+        }
+        Ok(consumed)
+    }
+}
+
+/// Splits a document into the spans of the recipes it contains.
+///
+/// Without a separator, a first level heading starts a new recipe once the current one has a
+/// title, unless it is an ingredient group between the first and second horizontal line.
+pub(crate) fn split_recipes(src: &str, separator: Option<&str>) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+
+    match separator {
+        Some(separator) => {
+            let mut pos = 0;
+            for line in src.split_inclusive('\n') {
+                if line.trim() == separator {
+                    spans.push(start..pos);
+                    start = pos + line.len();
+                }
+                pos += line.len();
+            }
+        }
+        None => {
+            let mut has_title = false;
+            let mut dividers = 0;
+            for node in crate::ast::parse(src) {
+                match node.kind {
+                    NodeKind::Heading { level: 1, .. } if has_title && dividers != 1 => {
+                        spans.push(start..node.span.start);
+                        start = node.span.start;
+                        dividers = 0;
+                    }
+                    NodeKind::Heading { level: 1, .. } => has_title = true,
+                    NodeKind::HorizontalLine => dividers += 1,
+                    _ => (),
+                }
+            }
+        }
+    }
+    spans.push(start..src.len());
+
+    spans.retain(|span| !src[span.clone()].trim().is_empty());
+    spans
+}
+
+pub(crate) fn parse_amount(src: &str, span: Range<usize>) -> Result<Amount> {
     let s = src[span.clone()].trim();
 
     // proper (1/2) or improper fraction (1 1/2)
@@ -343,7 +637,7 @@ fn parse_ingredient(src: &str, node: &Node) -> Result<Ingredient> {
             kind: NodeKind::Emphasis(amount_children),
             ..
         }, Node {
-            kind: NodeKind::Text(CowStr::Borrowed(" ")),
+            kind: NodeKind::Text(Cow::Borrowed(" ")),
             ..
         }, Node {
             kind:
@@ -362,6 +656,9 @@ fn parse_ingredient(src: &str, node: &Node) -> Result<Ingredient> {
                 amount,
                 name: src[link_children.span()].trim().to_owned(),
                 link: Some(escape_url(destination)),
+                note: None,
+                original_name: None,
+                alternatives: Vec::new(),
             })
         }
         [Node {
@@ -373,6 +670,9 @@ fn parse_ingredient(src: &str, node: &Node) -> Result<Ingredient> {
                 amount,
                 name: src[children.span()].trim().to_string(),
                 link: None,
+                note: None,
+                original_name: None,
+                alternatives: Vec::new(),
             })
         }
         [Node {
@@ -381,53 +681,587 @@ fn parse_ingredient(src: &str, node: &Node) -> Result<Ingredient> {
                     destination,
                     children,
                 },
-            ..
-        }] => Ok(Ingredient {
-            amount: None,
-            name: src[children.span()].trim().to_owned(),
-            link: Some(escape_url(destination)),
-        }),
+            span,
+        }] => {
+            if children.is_empty() {
+                return Err(Error::new(ErrorKind::EmptyIngredient, span.clone()));
+            }
+            Ok(Ingredient {
+                amount: None,
+                name: src[children.span()].trim().to_owned(),
+                link: Some(escape_url(destination)),
+                note: None,
+                original_name: None,
+                alternatives: Vec::new(),
+            })
+        }
         children => Ok(Ingredient {
             amount: None,
             name: src[children.span()].trim().to_owned(),
             link: None,
+            note: None,
+            original_name: None,
+            alternatives: Vec::new(),
         }),
     }
 }
+
+fn split_notes(ingredients: &mut [Ingredient], ingredient_groups: &mut [IngredientGroup]) {
+    for ingredient in ingredients {
+        if let Some((name, note)) = split_note(&ingredient.name) {
+            let name = std::mem::replace(&mut ingredient.name, name);
+            ingredient.original_name.get_or_insert(name);
+            ingredient.note = Some(match ingredient.note.take() {
+                Some(sublist_note) => format!("{note}, {sublist_note}"),
+                None => note,
+            });
+        }
+        split_notes(&mut ingredient.alternatives, &mut []);
+    }
+    for group in ingredient_groups {
+        split_notes(&mut group.ingredients, &mut group.ingredient_groups);
+    }
+}
+
+/// Splits text in parentheses and after the first comma from an ingredient name.
+fn split_note(name: &str) -> Option<(String, String)> {
+    let mut base = String::new();
+    let mut notes = Vec::new();
+    let mut depth = 0;
+    let mut note_start = 0;
+
+    for (i, c) in name.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    note_start = i + 1;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    notes.push(name[note_start..i].trim().to_owned());
+                }
+            }
+            ',' if depth == 0 => {
+                notes.push(name[i + 1..].trim().to_owned());
+                break;
+            }
+            _ if depth == 0 => base.push(c),
+            _ => (),
+        }
+    }
+    if depth > 0 {
+        // unbalanced parentheses
+        return None;
+    }
+
+    let base = base.split_whitespace().collect::<Vec<_>>().join(" ");
+    notes.retain(|note| !note.is_empty());
+    match (base.is_empty(), notes.is_empty()) {
+        (false, false) => Some((base, notes.join(", "))),
+        _ => None,
+    }
+}
+
+/// Moves alternatives written inline in the names of all ingredients into
+/// [`Ingredient::alternatives`].
+fn inline_alternatives(ingredients: &mut [Ingredient], ingredient_groups: &mut [IngredientGroup]) {
+    for ingredient in ingredients {
+        if let Some((name, alternatives)) = split_alternatives(&ingredient.name) {
+            ingredient.original_name = Some(std::mem::replace(&mut ingredient.name, name));
+            // inline alternatives come first, they are closer to the name than a nested list
+            ingredient.alternatives.splice(0..0, alternatives);
+        }
+        inline_alternatives(&mut ingredient.alternatives, &mut []);
+    }
+    for group in ingredient_groups {
+        inline_alternatives(&mut group.ingredients, &mut group.ingredient_groups);
+    }
+}
+
+/// Splits alternatives separated by `or` or in parentheses starting with `or` from an ingredient
+/// name.
+fn split_alternatives(name: &str) -> Option<(String, Vec<Ingredient>)> {
+    let mut base = String::new();
+    let mut parenthesized = Vec::new();
+    let mut depth = 0;
+    let mut group_start = 0;
+
+    for (i, c) in name.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    group_start = i;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let group = &name[group_start..=i];
+                    match regex!(r"(?i)^\(\s*(?:or|oder)\s+(.*)\)$").captures(group) {
+                        Some(m) => {
+                            parenthesized.push(m.get(1).unwrap().as_str());
+                            base.truncate(base.trim_end().len());
+                        }
+                        None => base.push_str(group),
+                    }
+                }
+            }
+            _ if depth == 0 => base.push(c),
+            _ => (),
+        }
+    }
+    if depth > 0 {
+        // unbalanced parentheses
+        return None;
+    }
+
+    let mut parts = split_or(&base).into_iter();
+    let name = parts
+        .next()?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let alternatives = parts
+        .chain(parenthesized.into_iter().flat_map(split_or))
+        .map(parse_alternative)
+        .collect::<Option<Vec<_>>>()?;
+    match (name.is_empty(), alternatives.is_empty()) {
+        (false, false) => Some((name, alternatives)),
+        _ => None,
+    }
+}
+
+/// Splits text at each `or` that is not in parentheses.
+fn split_or(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            _ if depth == 0 => {
+                if let Some(m) = regex!(r"(?i)^\s+(?:or|oder)\s+").find(&text[i..]) {
+                    parts.push(&text[start..i]);
+                    i += m.end();
+                    start = i;
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        i += c.len_utf8();
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Parses an inline alternative like `*150 g* honey`, `honey, 150 g` or `honey`.
+///
+/// Returns `None` if the alternative has no name.
+fn parse_alternative(text: &str) -> Option<Ingredient> {
+    let text = text.trim();
+    let parse = |amount: &str| parse_amount(amount, 0..amount.len()).ok();
+
+    let (amount, name) = if let Some(m) = regex!(r"^\*([^*]+)\*\s+(.+)$")
+        .captures(text)
+        .filter(|m| parse(&m[1]).is_some())
+    {
+        (parse(&m[1]), m.get(2).unwrap().as_str())
+    } else if let Some((name, amount)) = text.rsplit_once(',').filter(|(_, a)| parse(a).is_some()) {
+        (parse(amount), name)
+    } else {
+        (None, text)
+    };
+
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!name.is_empty()).then_some(Ingredient {
+        amount,
+        name,
+        link: None,
+        note: None,
+        original_name: None,
+        alternatives: Vec::new(),
+    })
+}
+
+/// Parses an ingredient list item, handling nested lists according to the options.
+fn parse_list_item(src: &str, options: &ParseOptions, item: &Node) -> Result<Ingredient> {
+    let NodeKind::ListItem(children) = &item.kind else {
+        panic!("ingredient must be a list item")
+    };
+    if options.sublists == SublistMode::Flatten {
+        return parse_ingredient(src, &item.flatten_paragraphs());
+    }
+
+    let (sublists, children): (Vec<Node>, Vec<Node>) = children
+        .iter()
+        .cloned()
+        .partition(|child| matches!(child.kind, NodeKind::List(_)));
+    let span = match children.is_empty() {
+        true => item.span.clone(),
+        false => children.span(),
+    };
+    let mut ingredient = parse_ingredient(
+        src,
+        &Node {
+            kind: NodeKind::ListItem(children),
+            span,
+        }
+        .flatten_paragraphs(),
+    )?;
+
+    let sub_items = sublists.iter().flat_map(|list| match &list.kind {
+        NodeKind::List(items) => items.as_slice(),
+        _ => &[],
+    });
+    match options.sublists {
+        SublistMode::Flatten => unreachable!("handled above"),
+        SublistMode::Notes => {
+            let notes: Vec<&str> = sub_items
+                .filter_map(|item| match &item.kind {
+                    NodeKind::ListItem(children) if !children.is_empty() => {
+                        Some(src[children.span()].trim())
+                    }
+                    _ => None,
+                })
+                .collect();
+            if !notes.is_empty() {
+                ingredient.note = Some(notes.join(", "));
+            }
+        }
+        SublistMode::Alternatives => {
+            ingredient.alternatives = sub_items
+                .map(|item| parse_list_item(src, options, item))
+                .collect::<Result<_>>()?;
+        }
+    }
+
+    Ok(ingredient)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn parse_ingredient_group<'s>(
     src: &str,
-    title: String,
-    level: HeadingLevel,
+    options: &ParseOptions,
+    source_map: &mut SourceMap,
+    extensions: &mut Extensions,
+    heading: Range<usize>,
+    title: Range<usize>,
+    level: u8,
     nodes: &mut Peekable<impl Iterator<Item = Node<'s>>>,
 ) -> Result<IngredientGroup> {
     let mut ingredients = Vec::new();
     let mut ingredient_groups = Vec::new();
 
+    // reserve the group's place before the spans of its nested groups are added
+    let index = source_map.ingredient_groups.len();
+    source_map.ingredient_groups.push(IngredientGroupSpans {
+        span: heading.clone(),
+        heading: heading.clone(),
+        title: title.clone(),
+        level,
+    });
+    let mut end = heading.end;
+
     while let Some(node) = nodes.peek() {
         match &node.kind {
             NodeKind::Heading {
                 level: child_level,
                 children,
             } if child_level > &level => {
-                let title = src[children.span()].to_owned();
+                let heading = trim_span(src, node.span.clone());
+                let title = heading_text_span(src, &node.span, children);
                 let child_level = *child_level;
                 let _ = nodes.next();
-                ingredient_groups.push(parse_ingredient_group(src, title, child_level, nodes)?);
+                let spans = GroupSpansStart::new(source_map);
+                let group = parse_ingredient_group(
+                    src,
+                    options,
+                    source_map,
+                    extensions,
+                    heading,
+                    title,
+                    child_level,
+                    nodes,
+                )?;
+                end = source_map.ingredient_groups[index + 1..]
+                    .iter()
+                    .map(|group| group.span.end)
+                    .fold(end, usize::max);
+                if !spans.consumed_by_extensions(src, options, source_map, extensions, &group)? {
+                    ingredient_groups.push(group);
+                }
             }
             NodeKind::List(items) => {
                 ingredients.reserve(items.len());
                 for item in items {
-                    ingredients.push(parse_ingredient(src, &item.flatten_paragraphs())?);
+                    ingredients.push(parse_list_item(src, options, item)?);
+                    source_map.ingredients.push(ingredient_spans(src, item));
                 }
+                end = trim_span(src, node.span.clone()).end;
+                let _ = nodes.next();
+            }
+            _ if apply_extensions(options, extensions, &node.span, |extension, data| {
+                extension.parse_ingredients_block(src, node, data)
+            })? =>
+            {
+                end = trim_span(src, node.span.clone()).end;
                 let _ = nodes.next();
             }
             _ => break,
         }
     }
+    source_map.ingredient_groups[index].span.end = end;
 
     Ok(IngredientGroup {
-        title,
+        title: src[title].to_owned(),
         ingredients,
         ingredient_groups,
     })
 }
+
+/// Returns the spans of an ingredient list item.
+fn ingredient_spans(src: &str, item: &Node) -> IngredientSpans {
+    let item = item.flatten_paragraphs();
+    let children = match &item.kind {
+        NodeKind::ListItem(children) => children.as_slice(),
+        _ => &[],
+    };
+
+    let amount = match children.first() {
+        Some(Node {
+            kind: NodeKind::Emphasis(amount_children),
+            ..
+        }) if !amount_children.is_empty() => Some(amount_children.span()),
+        _ => None,
+    };
+    let link = children.iter().find_map(|child| match child.kind {
+        NodeKind::Link { .. } => Some(child.span.clone()),
+        _ => None,
+    });
+
+    IngredientSpans {
+        span: trim_span(src, item.span.clone()),
+        amount,
+        link,
+    }
+}
+
+/// Returns the span of the text of a heading, which is empty and at the end of the heading if
+/// it has no text.
+fn heading_text_span(src: &str, heading: &Range<usize>, children: &[Node]) -> Range<usize> {
+    match children.is_empty() {
+        true => {
+            let end = trim_span(src, heading.clone()).end;
+            end..end
+        }
+        false => children.span(),
+    }
+}
+
+/// Shrinks a span so that it doesn't include leading or trailing whitespace.
+fn trim_span(src: &str, span: Range<usize>) -> Range<usize> {
+    let s = &src[span.clone()];
+    let start = span.start + (s.len() - s.trim_start().len());
+    let end = span.end - (s.len() - s.trim_end().len());
+    start..end.max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn split_ingredient_notes() {
+        let split = |name| split_note(name).map(|(name, note)| format!("{name} | {note}"));
+
+        assert_eq!(
+            split("onion, finely chopped").as_deref(),
+            Some("onion | finely chopped")
+        );
+        assert_eq!(
+            split("butter (softened)").as_deref(),
+            Some("butter | softened")
+        );
+        assert_eq!(
+            split("flour (type 550) for dusting, sifted").as_deref(),
+            Some("flour for dusting | type 550, sifted")
+        );
+        assert_eq!(split("(optional)"), None);
+        assert_eq!(split("salt, "), None);
+        assert_eq!(split("sugar (brown"), None);
+        assert_eq!(split("*ripe* tomatoes"), None);
+    }
+
+    #[test]
+    fn sublists_as_notes() {
+        let src = "# Soup\n\n---\n\n- *1* onion, diced\n\n  - or a shallot\n  - *keep the skin*\n\n- salt\n";
+        let options = ParseOptions::new()
+            .split_notes(true)
+            .sublists(SublistMode::Notes);
+        let recipe = RecipeParser::new(src)
+            .with_options(options)
+            .parse_recipe()
+            .unwrap();
+
+        assert_eq!(recipe.ingredients[0].name, "onion");
+        assert_eq!(
+            recipe.ingredients[0].note.as_deref(),
+            Some("diced, or a shallot, *keep the skin*")
+        );
+        assert_eq!(recipe.ingredients[1].name, "salt");
+        assert_eq!(recipe.ingredients[1].note, None);
+    }
+
+    #[test]
+    fn inline_alternatives() {
+        let split = |name: &str| {
+            split_alternatives(name).map(|(name, alternatives)| {
+                let alternatives = alternatives
+                    .iter()
+                    .map(|a| match &a.amount {
+                        Some(amount) => format!("{amount} {}", a.name),
+                        None => a.name.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                format!("{name} | {}", alternatives.join(" | "))
+            })
+        };
+
+        assert_eq!(
+            split("butter or margarine").as_deref(),
+            Some("butter | margarine")
+        );
+        assert_eq!(
+            split("sugar (or honey, 150 g)").as_deref(),
+            Some("sugar | 150 g honey")
+        );
+        assert_eq!(
+            split("Butter oder *80 ml* Öl (neutral)").as_deref(),
+            Some("Butter | 80 ml Öl (neutral)")
+        );
+        assert_eq!(
+            split("cream (or milk or yoghurt), cold").as_deref(),
+            Some("cream, cold | milk | yoghurt")
+        );
+        assert_eq!(split("salt (fine or coarse)").as_deref(), None);
+        assert_eq!(split("orange juice"), None);
+        assert_eq!(split("or honey"), None);
+        assert_eq!(split("butter or "), None);
+    }
+
+    #[test]
+    fn inline_alternatives_before_notes() {
+        let src = "# Cake\n\n---\n\n- *200 g* sugar (or honey, 150 g), sifted\n  - maple syrup\n";
+        let options = ParseOptions::new()
+            .split_notes(true)
+            .inline_alternatives(true)
+            .sublists(SublistMode::Alternatives);
+        let recipe = RecipeParser::new(src)
+            .with_options(options)
+            .parse_recipe()
+            .unwrap();
+
+        let sugar = &recipe.ingredients[0];
+        assert_eq!(sugar.name, "sugar");
+        assert_eq!(sugar.note.as_deref(), Some("sifted"));
+        assert_eq!(
+            sugar.original_name.as_deref(),
+            Some("sugar (or honey, 150 g), sifted")
+        );
+        let alternatives: Vec<&str> = sugar.alternatives.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(alternatives, ["honey", "maple syrup"]);
+    }
+
+    #[test]
+    fn empty_headings_and_links() {
+        let recipe = Recipe::parse("#\n\n---\n\n- flour\n\n##\n\n- cream\n").unwrap();
+        assert_eq!(recipe.title, "");
+        assert_eq!(recipe.ingredient_groups[0].title, "");
+        assert_eq!(recipe.ingredient_groups[0].ingredients[0].name, "cream");
+
+        let recipe = Recipe::parse("# Cake\n\n---\n\n## \n\n### #\n").unwrap();
+        assert_eq!(recipe.ingredient_groups[0].ingredient_groups[0].title, "");
+
+        for src in [
+            "# Cake\n\n---\n\n- [](flour.md)\n",
+            "# Cake\n\n---\n\n- *1* \n",
+        ] {
+            let error = Recipe::parse(src).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::EmptyIngredient, "{src:?}");
+        }
+    }
+
+    #[test]
+    fn split_many_recipes() {
+        let src = "\
+# Tea
+
+---
+
+# Leaves
+
+- *1* tea bag
+
+---
+
+Steep.
+
+# Lemonade
+
+---
+
+- *1* lemon
+
+---
+
+# Broken
+";
+        let spans = split_recipes(src, None);
+        let recipes: Vec<&str> = spans.iter().map(|span| &src[span.clone()]).collect();
+        assert_eq!(
+            recipes,
+            [
+                "# Tea\n\n---\n\n# Leaves\n\n- *1* tea bag\n\n---\n\nSteep.\n\n",
+                "# Lemonade\n\n---\n\n- *1* lemon\n\n---\n\n",
+                "# Broken\n",
+            ]
+        );
+
+        let recipes = Recipe::parse_many(src);
+        assert_eq!(
+            recipes[0].recipe.as_ref().unwrap().ingredient_groups[0].title,
+            "Leaves"
+        );
+        assert_eq!(recipes[1].recipe.as_ref().unwrap().title, "Lemonade");
+        let error = recipes[2].recipe.as_ref().unwrap_err();
+        assert_eq!(error.kind, ErrorKind::ExpectedHorizontalLine);
+        assert_eq!(error.span, Some(src.len()..src.len()));
+
+        assert!(split_recipes("", None).is_empty());
+        assert!(split_recipes("\n\n", Some("***")).is_empty());
+    }
+
+    #[test]
+    fn split_at_separator() {
+        let src = "+++\n\n# Tea\n\n---\n\n+++\n# Coffee\n\nStrong\n---\n+++\n";
+        let options = ParseOptions::new().recipe_separator("+++");
+        let recipes = Recipe::parse_many_with_options(src, &options);
+
+        assert_eq!(recipes.len(), 2);
+        assert_eq!(&src[recipes[0].span.clone()], "\n# Tea\n\n---\n\n");
+        assert_eq!(recipes[0].recipe.as_ref().unwrap().title, "Tea");
+        let error = recipes[1].recipe.as_ref().unwrap_err();
+        // `Strong` is a setext heading, so the horizontal line is missing
+        assert_eq!(error.kind, ErrorKind::ExpectedHorizontalLine);
+        assert_eq!(error.span, Some(src.len() - 4..src.len() - 4));
+    }
+}
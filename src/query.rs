@@ -0,0 +1,498 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! A small query language for finding recipes, e.g.
+//! `tag:vegan AND NOT ingredient:peanut AND yield>=4 servings`.
+//!
+//! # Syntax
+//!
+//! | Query                      | Matches recipes…                                                    |
+//! |----------------------------|---------------------------------------------------------------------|
+//! | `tag:vegan`                | with the tag "vegan" (ignoring case)                                |
+//! | `ingredient:peanut`        | with an ingredient whose name contains "peanut", including groups   |
+//! | `title:pizza`              | whose title contains "pizza"                                        |
+//! | `yield>=4 servings`        | that yield at least 4 servings (also `>`, `<`, `<=`, `=` and `:`)   |
+//! | `pizza`                    | whose title, tags or ingredients contain "pizza"                    |
+//! | `a AND b`, `a b`           | matching both `a` and `b`                                           |
+//! | `a OR b`                   | matching `a`, `b` or both                                           |
+//! | `NOT a`                    | not matching `a`                                                    |
+//! | `(a OR b) AND c`           | grouping                                                            |
+//!
+//! Values that contain whitespace or special characters can be quoted: `ingredient:"olive oil"`.
+//! `AND` binds stronger than `OR`. A yield without a unit matches yields with any unit.
+
+use std::{ops::Range, str::FromStr};
+
+use crate::Recipe;
+
+/// A parsed query that can be evaluated against recipes.
+///
+/// ```
+/// # use recipemd::{Recipe, query::Query};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let query = Query::parse("tag:vegan AND NOT ingredient:peanut AND yield>=4 servings")?;
+/// let recipe = Recipe::parse("# Salad\n\n*vegan*\n\n**4 servings**\n\n---\n\n- lettuce\n")?;
+///
+/// assert!(query.matches(&recipe));
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Tag(String),
+    Ingredient(String),
+    Title(String),
+    /// Matches the title, tags and ingredients.
+    Text(String),
+    Yield {
+        comparison: Comparison,
+        value: f32,
+        unit: Option<String>,
+    },
+}
+
+/// A comparison operator used in yield queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// The exact reason why parsing a query failed.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum QueryErrorKind {
+    #[error("unexpected end of query")]
+    UnexpectedEnd,
+    #[error("unexpected `{0}`")]
+    UnexpectedToken(String),
+    #[error("unknown field `{0}`, expected `tag`, `ingredient`, `title` or `yield`")]
+    UnknownField(String),
+    #[error("field `{0}` can't be compared, use `:` instead")]
+    InvalidComparison(String),
+    #[error("expected a number")]
+    ExpectedNumber,
+    #[error("missing closing parenthesis")]
+    UnclosedParenthesis,
+    #[error("missing closing quote")]
+    UnterminatedString,
+}
+
+/// Returned if parsing a query was not successful.
+///
+/// The exact byte range where the error occured can be retrieved from its `span` field.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("failed to parse query")]
+pub struct QueryError {
+    #[source]
+    pub kind: QueryErrorKind,
+    pub span: Range<usize>,
+    #[cfg(feature = "diagnostics")]
+    src: String,
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for QueryError {
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.src)
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        Some(Box::new(
+            [miette::LabeledSpan::at(
+                self.span.clone(),
+                self.kind.to_string(),
+            )]
+            .into_iter(),
+        ))
+    }
+}
+
+impl Query {
+    /// Parses a query string.
+    pub fn parse(src: &str) -> Result<Query, QueryError> {
+        let error = |kind, span| QueryError {
+            kind,
+            span,
+            #[cfg(feature = "diagnostics")]
+            src: src.to_owned(),
+        };
+        let tokens = tokenize(src).map_err(|(kind, span)| error(kind, span))?;
+
+        let mut parser = QueryParser {
+            tokens,
+            pos: 0,
+            end: src.len(),
+        };
+        let query = parser
+            .parse_or()
+            .map_err(|(kind, span)| error(kind, span))?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(query),
+            Some((token, span)) => Err(error(
+                QueryErrorKind::UnexpectedToken(token.to_string()),
+                span.clone(),
+            )),
+        }
+    }
+
+    /// Returns `true` if the recipe matches the query.
+    pub fn matches(&self, recipe: &Recipe) -> bool {
+        let contains =
+            |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+
+        match self {
+            Query::And(a, b) => a.matches(recipe) && b.matches(recipe),
+            Query::Or(a, b) => a.matches(recipe) || b.matches(recipe),
+            Query::Not(a) => !a.matches(recipe),
+            Query::Tag(tag) => recipe
+                .tags
+                .iter()
+                .any(|t| t.to_lowercase() == tag.to_lowercase()),
+            Query::Ingredient(name) => recipe.all_ingredients().any(|i| contains(&i.name, name)),
+            Query::Title(title) => contains(&recipe.title, title),
+            Query::Text(text) => {
+                contains(&recipe.title, text)
+                    || recipe.tags.iter().any(|t| contains(t, text))
+                    || recipe.all_ingredients().any(|i| contains(&i.name, text))
+            }
+            Query::Yield {
+                comparison,
+                value,
+                unit,
+            } => recipe.yields.iter().any(|amount| {
+                let unit_matches = match (unit, &amount.unit) {
+                    (None, _) => true,
+                    (Some(a), Some(b)) => a.to_lowercase() == b.to_lowercase(),
+                    (Some(_), None) => false,
+                };
+                let factor = f32::from(amount.factor);
+                unit_matches
+                    && match comparison {
+                        Comparison::Equal => factor == *value,
+                        Comparison::Less => factor < *value,
+                        Comparison::LessOrEqual => factor <= *value,
+                        Comparison::Greater => factor > *value,
+                        Comparison::GreaterOrEqual => factor >= *value,
+                    }
+            }),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(src: &str) -> Result<Self, QueryError> {
+        Query::parse(src)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Colon,
+    Comparison(Comparison),
+    OpenParen,
+    CloseParen,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Quoted(s) => write!(f, "\"{s}\""),
+            Token::Colon => write!(f, ":"),
+            Token::Comparison(Comparison::Equal) => write!(f, "="),
+            Token::Comparison(Comparison::Less) => write!(f, "<"),
+            Token::Comparison(Comparison::LessOrEqual) => write!(f, "<="),
+            Token::Comparison(Comparison::Greater) => write!(f, ">"),
+            Token::Comparison(Comparison::GreaterOrEqual) => write!(f, ">="),
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+        }
+    }
+}
+
+type ParseResult<T> = Result<T, (QueryErrorKind, Range<usize>)>;
+
+fn tokenize(src: &str) -> ParseResult<Vec<(Token, Range<usize>)>> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            ':' => Token::Colon,
+            '=' => Token::Comparison(Comparison::Equal),
+            '<' | '>' => {
+                let or_equal = chars.next_if(|(_, c)| *c == '=').is_some();
+                Token::Comparison(match (c, or_equal) {
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessOrEqual,
+                    (_, false) => Comparison::Greater,
+                    (_, true) => Comparison::GreaterOrEqual,
+                })
+            }
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => s.push(c),
+                            None => {
+                                return Err((QueryErrorKind::UnterminatedString, start..src.len()))
+                            }
+                        },
+                        Some((_, c)) => s.push(c),
+                        None => return Err((QueryErrorKind::UnterminatedString, start..src.len())),
+                    }
+                }
+                Token::Quoted(s)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !"():=<>\"".contains(*c))
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+        };
+        let end = chars.peek().map_or(src.len(), |(i, _)| *i);
+        tokens.push((token, start..end));
+    }
+
+    Ok(tokens)
+}
+
+struct QueryParser {
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+    end: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+    fn next(&mut self) -> ParseResult<(Token, Range<usize>)> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or((QueryErrorKind::UnexpectedEnd, self.end..self.end))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> ParseResult<Query> {
+        let mut query = self.parse_and()?;
+        while self.peek_keyword("OR") {
+            self.pos += 1;
+            query = Query::Or(Box::new(query), Box::new(self.parse_and()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Query> {
+        let mut query = self.parse_unary()?;
+        loop {
+            if self.peek_keyword("AND") {
+                self.pos += 1;
+            } else if self.peek().is_none()
+                || self.peek_keyword("OR")
+                || self.peek() == Some(&Token::CloseParen)
+            {
+                break;
+            }
+            query = Query::And(Box::new(query), Box::new(self.parse_unary()?));
+        }
+        Ok(query)
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Query> {
+        match self.next()? {
+            (Token::Word(word), _) if word == "NOT" => {
+                Ok(Query::Not(Box::new(self.parse_unary()?)))
+            }
+            (Token::OpenParen, span) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Ok((Token::CloseParen, _)) => Ok(query),
+                    _ => Err((QueryErrorKind::UnclosedParenthesis, span)),
+                }
+            }
+            (Token::Word(word), span) => match self.peek() {
+                Some(Token::Colon | Token::Comparison(_)) => self.parse_field(word, span),
+                _ if ["AND", "OR"].contains(&word.as_str()) => {
+                    Err((QueryErrorKind::UnexpectedToken(word), span))
+                }
+                _ => Ok(Query::Text(word)),
+            },
+            (Token::Quoted(s), _) => Ok(Query::Text(s)),
+            (token, span) => Err((QueryErrorKind::UnexpectedToken(token.to_string()), span)),
+        }
+    }
+
+    fn parse_field(&mut self, field: String, field_span: Range<usize>) -> ParseResult<Query> {
+        let (operator, operator_span) = self.next()?;
+        let comparison = match operator {
+            Token::Colon | Token::Comparison(Comparison::Equal) => Comparison::Equal,
+            Token::Comparison(comparison) => comparison,
+            _ => unreachable!("checked by caller"),
+        };
+
+        let constructor = match field.to_lowercase().as_str() {
+            "yield" | "yields" => return self.parse_yield(comparison),
+            "tag" | "tags" => Query::Tag,
+            "ingredient" | "ingredients" => Query::Ingredient,
+            "title" => Query::Title,
+            _ => return Err((QueryErrorKind::UnknownField(field), field_span)),
+        };
+        if comparison != Comparison::Equal {
+            return Err((QueryErrorKind::InvalidComparison(field), operator_span));
+        }
+
+        match self.next()? {
+            (Token::Word(value) | Token::Quoted(value), _) => Ok(constructor(value)),
+            (token, span) => Err((QueryErrorKind::UnexpectedToken(token.to_string()), span)),
+        }
+    }
+
+    fn parse_yield(&mut self, comparison: Comparison) -> ParseResult<Query> {
+        let (token, span) = self.next()?;
+        let value = match &token {
+            Token::Word(word) => word
+                .replace(',', ".")
+                .parse()
+                .map_err(|_| (QueryErrorKind::ExpectedNumber, span))?,
+            _ => return Err((QueryErrorKind::ExpectedNumber, span)),
+        };
+
+        let is_unit = |token: Option<&(Token, Range<usize>)>| match token {
+            Some((Token::Word(word), _)) => !["AND", "OR", "NOT"].contains(&word.as_str()),
+            Some((Token::Quoted(_), _)) => true,
+            _ => false,
+        };
+        // a word followed by an operator is the next field instead of a unit
+        let unit = match is_unit(self.tokens.get(self.pos))
+            && !matches!(
+                self.tokens.get(self.pos + 1),
+                Some((Token::Colon | Token::Comparison(_), _))
+            ) {
+            true => match self.next()? {
+                (Token::Word(unit) | Token::Quoted(unit), _) => Some(unit),
+                _ => unreachable!("checked by is_unit"),
+            },
+            false => None,
+        };
+
+        Ok(Query::Yield {
+            comparison,
+            value,
+            unit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn tag(s: &str) -> Box<Query> {
+        Box::new(Query::Tag(s.to_owned()))
+    }
+
+    #[test]
+    fn parse_query() {
+        assert_eq!(
+            Query::parse(
+                "tag:vegan AND NOT ingredient:\"peanut butter\" OR (yield>=4 servings tag=x)"
+            )
+            .unwrap(),
+            Query::Or(
+                Box::new(Query::And(
+                    tag("vegan"),
+                    Box::new(Query::Not(Box::new(Query::Ingredient(
+                        "peanut butter".to_owned()
+                    ))))
+                )),
+                Box::new(Query::And(
+                    Box::new(Query::Yield {
+                        comparison: Comparison::GreaterOrEqual,
+                        value: 4.0,
+                        unit: Some("servings".to_owned())
+                    }),
+                    tag("x")
+                ))
+            )
+        );
+        assert_eq!(
+            Query::parse("cake yield<2").unwrap(),
+            Query::And(
+                Box::new(Query::Text("cake".to_owned())),
+                Box::new(Query::Yield {
+                    comparison: Comparison::Less,
+                    value: 2.0,
+                    unit: None
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn query_errors() {
+        let error = |src| {
+            let e = Query::parse(src).unwrap_err();
+            (e.kind, e.span)
+        };
+
+        assert_eq!(
+            error("color:red"),
+            (QueryErrorKind::UnknownField("color".to_owned()), 0..5)
+        );
+        assert_eq!(
+            error("tag>vegan"),
+            (QueryErrorKind::InvalidComparison("tag".to_owned()), 3..4)
+        );
+        assert_eq!(
+            error("yield>=many"),
+            (QueryErrorKind::ExpectedNumber, 7..11)
+        );
+        assert_eq!(
+            error("(tag:a OR b"),
+            (QueryErrorKind::UnclosedParenthesis, 0..1)
+        );
+        assert_eq!(error("tag:\"a"), (QueryErrorKind::UnterminatedString, 4..6));
+        assert_eq!(error("tag:a AND"), (QueryErrorKind::UnexpectedEnd, 9..9));
+    }
+
+    #[test]
+    fn evaluate_query() {
+        let recipe = Recipe::parse(
+            "# Peanut Noodles\n\n*Vegan, asian*\n\n**2 servings**\n\n---\n\n## Sauce\n\n- *2 tbsp* peanut butter\n",
+        )
+        .unwrap();
+        let matches = |src| Query::parse(src).unwrap().matches(&recipe);
+
+        assert!(matches("tag:vegan"));
+        assert!(!matches("tag:vegan AND NOT ingredient:peanut"));
+        assert!(matches("noodles yield=2 servings"));
+        assert!(!matches("yield>=4"));
+        assert!(matches("yield>=4 OR title:noodle"));
+    }
+}
@@ -0,0 +1,646 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Render recipes as formatted plain text, e.g. for displaying them in a terminal.
+
+use lazy_regex::regex;
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::{Ingredient, IngredientGroup, Recipe};
+
+const BOLD: &str = "\x1b[1m";
+const ITALIC: &str = "\x1b[3m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders a [`Recipe`] as nicely formatted plain text.
+///
+/// Ingredient amounts, units and names are aligned in columns, ingredient groups are indented
+/// according to their nesting level and markdown in the description and instructions is
+/// stripped. Optionally, text can be wrapped to a maximum width and styled using ANSI escape
+/// codes.
+///
+/// ```
+/// # use recipemd::{Recipe, render::TextRenderer};
+/// # fn main() -> recipemd::Result<()> {
+/// let recipe = Recipe::parse("# Water\n\n---\n\n- *1* glass\n- faucet\n")?;
+/// let text = TextRenderer::new().width(80).render(&recipe);
+///
+/// assert_eq!(text, "Water\n=====\n\nIngredients\n-----------\n\n  1 glass\n    faucet\n");
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TextRenderer {
+    width: Option<usize>,
+    ansi: bool,
+}
+
+impl TextRenderer {
+    /// Creates a renderer that neither wraps lines nor uses ANSI escape codes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap lines so that they don't exceed the given width (in characters).
+    pub fn width(mut self, width: impl Into<Option<usize>>) -> Self {
+        self.width = width.into();
+        self
+    }
+
+    /// Style headings and emphasized text using ANSI escape codes.
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Renders the recipe as plain text.
+    pub fn render(&self, recipe: &Recipe) -> String {
+        let mut w = Writer::new(self);
+
+        w.heading(&recipe.title, '=');
+
+        if let Some(description) = &recipe.description {
+            w.markdown(description);
+        }
+
+        if !recipe.tags.is_empty() || !recipe.yields.is_empty() {
+            w.start_block();
+            if !recipe.tags.is_empty() {
+                w.labeled("Tags:   ", &recipe.tags.join(", "));
+            }
+            if !recipe.yields.is_empty() {
+                let yields = recipe
+                    .yields
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                w.labeled("Yields: ", &yields);
+            }
+        }
+
+        if !recipe.ingredients.is_empty() || !recipe.ingredient_groups.is_empty() {
+            w.heading("Ingredients", '-');
+            w.start_block();
+
+            let columns = Columns::measure(&recipe.ingredients, &recipe.ingredient_groups);
+            w.ingredients(&columns, "  ", &recipe.ingredients);
+            for group in &recipe.ingredient_groups {
+                w.ingredient_group(&columns, "  ", group);
+            }
+        }
+
+        if let Some(instructions) = &recipe.instructions {
+            w.heading("Instructions", '-');
+            w.markdown(instructions);
+        }
+
+        w.out
+    }
+}
+
+/// Widths of the amount and unit columns of an ingredient list.
+struct Columns {
+    factor: usize,
+    unit: usize,
+}
+
+impl Columns {
+    fn measure(ingredients: &[Ingredient], groups: &[IngredientGroup]) -> Self {
+        let mut columns = Columns { factor: 0, unit: 0 };
+        columns.extend(ingredients, groups);
+        columns
+    }
+    fn extend(&mut self, ingredients: &[Ingredient], groups: &[IngredientGroup]) {
+        for amount in ingredients.iter().filter_map(|i| i.amount.as_ref()) {
+            self.factor = self.factor.max(amount.factor.to_string().chars().count());
+            if let Some(unit) = &amount.unit {
+                self.unit = self.unit.max(unit.chars().count());
+            }
+        }
+        for group in groups {
+            self.extend(&group.ingredients, &group.ingredient_groups);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Style {
+    bold: usize,
+    italic: usize,
+}
+
+/// A word that may consist of differently styled segments, e.g. `*un*believable`.
+enum Token {
+    Word(Vec<(String, Style)>),
+    Break,
+}
+
+struct Writer<'r> {
+    renderer: &'r TextRenderer,
+    out: String,
+    indents: Vec<String>,
+    marker: Option<String>,
+    lists: Vec<Option<u64>>,
+    tokens: Vec<Token>,
+    style: Style,
+    space: bool,
+}
+
+impl<'r> Writer<'r> {
+    fn new(renderer: &'r TextRenderer) -> Self {
+        Self {
+            renderer,
+            out: String::new(),
+            indents: Vec::new(),
+            marker: None,
+            lists: Vec::new(),
+            tokens: Vec::new(),
+            style: Style::default(),
+            space: false,
+        }
+    }
+
+    /// Separates the next block from the previous output with an empty line.
+    fn start_block(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn heading(&mut self, text: &str, underline: char) {
+        self.start_block();
+        self.style.bold += 1;
+        self.inline_markdown(text);
+        self.style.bold -= 1;
+        let width = self.flush("", "");
+        if !self.renderer.ansi {
+            self.out.extend(std::iter::repeat_n(underline, width));
+            self.out.push('\n');
+        }
+    }
+
+    fn labeled(&mut self, label: &str, text: &str) {
+        let rest = " ".repeat(label.chars().count());
+        let first = match self.renderer.ansi {
+            true => format!(
+                "{BOLD}{}{RESET}{}",
+                label.trim_end(),
+                &label[label.trim_end().len()..]
+            ),
+            false => label.to_owned(),
+        };
+        self.text(text);
+        self.flush(&first, &rest);
+    }
+
+    fn ingredients(&mut self, columns: &Columns, indent: &str, ingredients: &[Ingredient]) {
+        for ingredient in ingredients {
+            let mut prefix = indent.to_owned();
+            if columns.factor > 0 {
+                let (factor, unit) = match &ingredient.amount {
+                    Some(amount) => (amount.factor.to_string(), amount.unit.as_deref()),
+                    None => (String::new(), None),
+                };
+                prefix.push_str(&format!("{factor:>width$} ", width = columns.factor));
+                if columns.unit > 0 {
+                    prefix.push_str(&format!(
+                        "{:<width$} ",
+                        unit.unwrap_or_default(),
+                        width = columns.unit
+                    ));
+                }
+            }
+            let rest = " ".repeat(prefix.chars().count());
+
+            self.ingredient_name(ingredient);
+            self.flush(&prefix, &rest);
+
+            for alternative in &ingredient.alternatives {
+                self.text("or ");
+                if let Some(amount) = &alternative.amount {
+                    self.text(&format!("{amount} "));
+                }
+                self.ingredient_name(alternative);
+                self.flush(&rest, &format!("{rest}   "));
+            }
+        }
+    }
+
+    fn ingredient_name(&mut self, ingredient: &Ingredient) {
+        self.inline_markdown(&ingredient.name);
+        if let Some(note) = &ingredient.note {
+            self.text(", ");
+            self.inline_markdown(note);
+        }
+        if let Some(link) = &ingredient.link {
+            self.space = true;
+            self.text(&format!("<{link}>"));
+        }
+    }
+
+    fn ingredient_group(&mut self, columns: &Columns, indent: &str, group: &IngredientGroup) {
+        self.start_block();
+        self.style.bold += 1;
+        self.inline_markdown(&group.title);
+        self.style.bold -= 1;
+        self.flush(indent, indent);
+
+        let indent = format!("{indent}  ");
+        self.ingredients(columns, &indent, &group.ingredients);
+        for group in &group.ingredient_groups {
+            self.ingredient_group(columns, &indent, group);
+        }
+    }
+
+    /// Renders a markdown document while stripping or styling its markup.
+    fn markdown(&mut self, src: &str) {
+        let mut code_block: Option<String> = None;
+
+        for event in Parser::new(src) {
+            match event {
+                Event::Start(Tag::Paragraph) => {
+                    self.flush_pending();
+                    self.start_block();
+                }
+                Event::End(TagEnd::Paragraph) => self.flush_pending(),
+                Event::Start(Tag::Heading { .. }) => {
+                    self.flush_pending();
+                    self.start_block();
+                    self.style.bold += 1;
+                }
+                Event::End(TagEnd::Heading(_)) => {
+                    self.style.bold -= 1;
+                    self.flush_pending();
+                }
+                Event::Start(Tag::BlockQuote(_)) => {
+                    self.flush_pending();
+                    self.start_block();
+                    self.indents.push("│ ".to_owned());
+                }
+                Event::End(TagEnd::BlockQuote(_)) => {
+                    self.flush_pending();
+                    self.indents.pop();
+                }
+                Event::Start(Tag::List(start)) => {
+                    self.flush_pending();
+                    if self.lists.is_empty() {
+                        self.start_block();
+                    }
+                    self.lists.push(start);
+                }
+                Event::End(TagEnd::List(_)) => {
+                    self.flush_pending();
+                    self.lists.pop();
+                }
+                Event::Start(Tag::Item) => {
+                    self.flush_pending();
+                    let marker = match self.lists.last_mut() {
+                        Some(Some(n)) => {
+                            *n += 1;
+                            format!("{}. ", *n - 1)
+                        }
+                        _ => "- ".to_owned(),
+                    };
+                    self.indents.push(" ".repeat(marker.chars().count()));
+                    self.marker = Some(marker);
+                }
+                Event::End(TagEnd::Item) => {
+                    self.flush_pending();
+                    self.indents.pop();
+                }
+                Event::Start(Tag::CodeBlock(_)) => {
+                    self.flush_pending();
+                    self.start_block();
+                    code_block = Some(String::new());
+                }
+                Event::End(TagEnd::CodeBlock) => {
+                    let code = code_block.take().unwrap_or_default();
+                    let prefix = format!("{}    ", self.indents.concat());
+                    for line in code.trim_end_matches('\n').lines() {
+                        match self.renderer.ansi {
+                            true => self.out.push_str(&format!("{prefix}{DIM}{line}{RESET}\n")),
+                            false => self.out.push_str(&format!("{prefix}{line}\n")),
+                        }
+                    }
+                }
+                Event::Rule => {
+                    self.flush_pending();
+                    self.start_block();
+                    let prefix = self.indents.concat();
+                    self.out.push_str(&format!("{prefix}---\n"));
+                }
+                Event::Text(text) if code_block.is_some() => {
+                    code_block.get_or_insert_default().push_str(&text)
+                }
+                event => self.inline_event(event),
+            }
+        }
+        self.flush_pending();
+    }
+
+    /// Renders inline markdown, ignoring any block structure.
+    fn inline_markdown(&mut self, src: &str) {
+        for event in Parser::new(&escape_block_markers(src)) {
+            self.inline_event(event);
+        }
+    }
+
+    fn inline_event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Emphasis) => self.style.italic += 1,
+            Event::End(TagEnd::Emphasis) => self.style.italic -= 1,
+            Event::Start(Tag::Strong) => self.style.bold += 1,
+            Event::End(TagEnd::Strong) => self.style.bold -= 1,
+            Event::Text(text) | Event::Code(text) => self.text(&text),
+            Event::SoftBreak => self.space = true,
+            Event::HardBreak => self.tokens.push(Token::Break),
+            _ => (),
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+                continue;
+            }
+            let style = self.style;
+            match self.tokens.last_mut() {
+                Some(Token::Word(segments)) if !self.space => match segments.last_mut() {
+                    Some((s, segment_style)) if *segment_style == style => s.push(c),
+                    _ => segments.push((c.to_string(), style)),
+                },
+                _ => self.tokens.push(Token::Word(vec![(c.to_string(), style)])),
+            }
+            self.space = false;
+        }
+    }
+
+    /// Writes the collected words using the current indentation and list marker.
+    fn flush_pending(&mut self) {
+        if self.tokens.is_empty() && self.marker.is_none() {
+            return;
+        }
+        let rest = self.indents.concat();
+        let first = match self.marker.take() {
+            Some(marker) => {
+                let outer = &self.indents[..self.indents.len().saturating_sub(1)];
+                format!("{}{marker}", outer.concat())
+            }
+            None => rest.clone(),
+        };
+        self.flush(&first, &rest);
+    }
+
+    /// Wraps and writes the collected words and returns the width of the longest line.
+    ///
+    /// The visible width of `first` must be equal to that of `rest`.
+    fn flush(&mut self, first: &str, rest: &str) -> usize {
+        let lines: Vec<_> = self
+            .wrap(rest.chars().count())
+            .iter()
+            .map(|line| self.render_line(line))
+            .collect();
+        let max_width = lines.iter().map(|(_, width)| *width).max().unwrap_or(0);
+
+        for (i, (rendered, _)) in lines.iter().enumerate() {
+            self.out.push_str(if i == 0 { first } else { rest });
+            self.out.push_str(rendered);
+            self.out.push('\n');
+        }
+        if lines.is_empty() && !first.trim().is_empty() {
+            self.out.push_str(first.trim_end());
+            self.out.push('\n');
+        }
+
+        self.tokens.clear();
+        self.space = false;
+        max_width
+    }
+
+    /// Greedily distributes the collected tokens over lines that fit into the available width.
+    fn wrap(&self, indent: usize) -> Vec<Vec<&Token>> {
+        let available = self
+            .renderer
+            .width
+            .map(|width| width.saturating_sub(indent).max(1))
+            .unwrap_or(usize::MAX);
+
+        let mut lines: Vec<Vec<&Token>> = Vec::new();
+        let mut current: Vec<&Token> = Vec::new();
+        let mut current_width = 0;
+
+        for token in &self.tokens {
+            match token {
+                Token::Break => {
+                    lines.push(std::mem::take(&mut current));
+                    current_width = 0;
+                }
+                Token::Word(segments) => {
+                    let width: usize = segments.iter().map(|(s, _)| s.chars().count()).sum();
+                    if !current.is_empty() && current_width + 1 + width > available {
+                        lines.push(std::mem::take(&mut current));
+                        current_width = 0;
+                    }
+                    if !current.is_empty() {
+                        current_width += 1;
+                    }
+                    current_width += width;
+                    current.push(token);
+                }
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+
+        lines
+    }
+
+    /// Joins the words of a line and returns it together with its visible width.
+    fn render_line(&self, line: &[&Token]) -> (String, usize) {
+        let mut rendered = String::new();
+        let mut width = 0;
+
+        for (i, token) in line.iter().enumerate() {
+            let Token::Word(segments) = token else {
+                continue;
+            };
+            if i > 0 {
+                rendered.push(' ');
+                width += 1;
+            }
+            for (s, style) in segments {
+                width += s.chars().count();
+                if !self.renderer.ansi || *style == Style::default() {
+                    rendered.push_str(s);
+                    continue;
+                }
+                if style.bold > 0 {
+                    rendered.push_str(BOLD);
+                }
+                if style.italic > 0 {
+                    rendered.push_str(ITALIC);
+                }
+                rendered.push_str(s);
+                rendered.push_str(RESET);
+            }
+        }
+
+        (rendered, width)
+    }
+}
+
+/// Escapes markdown syntax at the start of each line that would otherwise start a block, e.g.
+/// the list marker in a title like `1. Mai Bowle`, so that the text is parsed as inline content.
+fn escape_block_markers(src: &str) -> String {
+    let mut escaped = String::with_capacity(src.len());
+    for (i, line) in src.split('\n').enumerate() {
+        if i > 0 {
+            escaped.push('\n');
+        }
+        let line = line.trim_start();
+        if let Some(m) = regex!(r"^\d{1,9}[.)]").find(line) {
+            let (number, rest) = line.split_at(m.end() - 1);
+            escaped.push_str(number);
+            escaped.push('\\');
+            escaped.push_str(rest);
+            continue;
+        }
+        if regex!(r"^(?:[#>+=~-]|[*_](?:\s|$)|[*_][*_ \t]*$|```|\[[^\]]*\]:)").is_match(line) {
+            escaped.push('\\');
+        }
+        escaped.push_str(line);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{ParseOptions, SublistMode};
+
+    const MARKDOWN: &str = r#"# Pancakes
+
+Fluffy *and* **tasty** pancakes that everyone will love.
+
+*breakfast, sweet*
+
+**4 servings, 12 pancakes**
+
+---
+
+- *250 g* flour
+- *1/2 l* milk
+- *2* eggs
+- salt
+
+## Topping
+
+- *100 ml* [maple syrup](syrup.md)
+
+---
+
+1. Mix all ingredients.
+2. Fry in a hot pan until golden brown on both sides.
+"#;
+
+    #[test]
+    fn render_plain() {
+        let recipe = Recipe::parse(MARKDOWN).unwrap();
+
+        assert_eq!(
+            TextRenderer::new().width(40).render(&recipe),
+            r#"Pancakes
+========
+
+Fluffy and tasty pancakes that everyone
+will love.
+
+Tags:   breakfast, sweet
+Yields: 4 servings, 12 pancakes
+
+Ingredients
+-----------
+
+  250 g  flour
+  1/2 l  milk
+    2    eggs
+         salt
+
+  Topping
+    100 ml maple syrup <syrup.md>
+
+Instructions
+------------
+
+1. Mix all ingredients.
+2. Fry in a hot pan until golden brown
+   on both sides.
+"#
+        );
+    }
+
+    #[test]
+    fn render_notes_and_alternatives() {
+        let options = ParseOptions::new()
+            .split_notes(true)
+            .sublists(SublistMode::Alternatives);
+        let recipe = Recipe::parse_with_options(
+            "# Cake\n\n---\n\n- *100 g* butter (softened)\n  - *80 ml* vegetable oil\n  - margarine\n",
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(
+            TextRenderer::new().width(40).render(&recipe),
+            r#"Cake
+====
+
+Ingredients
+-----------
+
+  100 g butter, softened
+        or 80 ml vegetable oil
+        or margarine
+"#
+        );
+    }
+
+    #[test]
+    fn render_block_markers() {
+        let recipe = Recipe::parse(
+            "# 1. Mai Bowle\n\n---\n\n- *1* > bottle of wine\n- *2* # 2) sugar\n\n## - Extras\n\n- *1* *sweet* woodruff\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            TextRenderer::new().width(40).render(&recipe),
+            r#"1. Mai Bowle
+============
+
+Ingredients
+-----------
+
+  1 > bottle of wine
+  2 # 2) sugar
+
+  - Extras
+    1 sweet woodruff
+"#
+        );
+    }
+
+    #[test]
+    fn render_ansi() {
+        let recipe = Recipe::parse("# Tea\n\nVery *hot*.\n\n---\n\n---\n").unwrap();
+
+        assert_eq!(
+            TextRenderer::new().ansi(true).render(&recipe),
+            "\x1b[1mTea\x1b[0m\n\nVery \x1b[3mhot\x1b[0m.\n"
+        );
+    }
+}
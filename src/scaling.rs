@@ -0,0 +1,131 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+use crate::{Amount, Factor, Ingredient, IngredientGroup, Recipe};
+
+impl Factor {
+    /// Multiplies the factor by a number, keeping integers and fractions exact if possible.
+    pub fn scale(self, factor: f32) -> Factor {
+        let is_integer = factor.fract() == 0.0 && factor >= 0.0;
+        match self {
+            Factor::Integer(v) if is_integer && factor <= u32::MAX as f32 => {
+                match v.checked_mul(factor as u32) {
+                    Some(v) => Factor::Integer(v),
+                    None => Factor::Float(f32::from(self) * factor),
+                }
+            }
+            Factor::Fraction(num, denom) if is_integer && factor <= u16::MAX as f32 => {
+                match num.checked_mul(factor as u16) {
+                    Some(num) => Factor::Fraction(num, denom),
+                    None => Factor::Float(f32::from(self) * factor),
+                }
+            }
+            _ => Factor::Float(f32::from(self) * factor),
+        }
+    }
+}
+
+impl Amount {
+    /// Multiplies the amount by a number.
+    pub fn scale(&self, factor: f32) -> Amount {
+        Amount {
+            factor: self.factor.scale(factor),
+            unit: self.unit.clone(),
+        }
+    }
+}
+
+impl Recipe {
+    /// Multiplies the yields and all ingredient amounts by a number.
+    pub fn scale(&self, factor: f32) -> Recipe {
+        fn scale_ingredients(ingredients: &[Ingredient], factor: f32) -> Vec<Ingredient> {
+            ingredients
+                .iter()
+                .map(|ingredient| Ingredient {
+                    amount: ingredient.amount.as_ref().map(|a| a.scale(factor)),
+                    alternatives: scale_ingredients(&ingredient.alternatives, factor),
+                    ..ingredient.clone()
+                })
+                .collect()
+        }
+        fn scale_groups(groups: &[IngredientGroup], factor: f32) -> Vec<IngredientGroup> {
+            groups
+                .iter()
+                .map(|group| IngredientGroup {
+                    title: group.title.clone(),
+                    ingredients: scale_ingredients(&group.ingredients, factor),
+                    ingredient_groups: scale_groups(&group.ingredient_groups, factor),
+                })
+                .collect()
+        }
+
+        Recipe {
+            yields: self.yields.iter().map(|y| y.scale(factor)).collect(),
+            ingredients: scale_ingredients(&self.ingredients, factor),
+            ingredient_groups: scale_groups(&self.ingredient_groups, factor),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the factor needed to scale the recipe to the given yield.
+    ///
+    /// The yield is compared to the first of the recipe's yields that has the same or a
+    /// convertible unit. Returns `None` if there is no such yield.
+    pub fn scale_factor_for(&self, target: &Amount) -> Option<f32> {
+        self.yields.iter().find_map(|amount| {
+            let current = f32::from(amount.convert_to(target.unit.as_deref())?.factor);
+            (current > 0.0).then(|| f32::from(target.factor) / current)
+        })
+    }
+
+    /// Scales the recipe to the given yield.
+    ///
+    /// See [`Recipe::scale_factor_for`] for how the yield is matched.
+    ///
+    /// ```
+    /// # use recipemd::Recipe;
+    /// # fn main() -> recipemd::Result<()> {
+    /// let recipe = Recipe::parse("# Pancakes\n\n**4 servings, 12 pancakes**\n\n---\n\n- *250 ml* milk\n- *2* eggs\n")?;
+    /// let scaled = recipe.scale_to(&"6 pancakes".parse()?).unwrap();
+    ///
+    /// assert_eq!(scaled.yields[0].to_string(), "2 servings");
+    /// assert_eq!(scaled.ingredients[0].amount.as_ref().unwrap().to_string(), "125 ml");
+    /// assert_eq!(scaled.ingredients[1].amount.as_ref().unwrap().to_string(), "1");
+    /// # Ok(()) }
+    /// ```
+    pub fn scale_to(&self, target: &Amount) -> Option<Recipe> {
+        Some(self.scale(self.scale_factor_for(target)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn scale_factors() {
+        assert_eq!(Factor::Integer(3).scale(2.0), Factor::Integer(6));
+        assert_eq!(Factor::Fraction(1, 2).scale(3.0), Factor::Fraction(3, 2));
+        assert_eq!(Factor::Integer(3).scale(0.5), Factor::Float(1.5));
+        assert_eq!(Factor::Float(0.5).scale(2.0), Factor::Float(1.0));
+        assert_eq!(
+            Factor::Integer(100_000).scale(100_000.0),
+            Factor::Float(1e10)
+        );
+        assert_eq!(Factor::Integer(2).scale(1e10), Factor::Float(2e10));
+    }
+
+    #[test]
+    fn scale_to_large_yields() {
+        let recipe = Recipe::parse("# Soup\n\n**1 serving**\n\n---\n\n- *100000* peas\n").unwrap();
+        let scaled = recipe
+            .scale_to(&"100000 servings".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            scaled.ingredients[0].amount.as_ref().unwrap().factor,
+            Factor::Float(1e10)
+        );
+    }
+}
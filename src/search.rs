@@ -0,0 +1,558 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! An in-memory full-text search index over recipes.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+use crate::{collection::Collection, normalize::Canonicalizer, utils::strip_markdown, Recipe};
+
+const FORMAT_HEADER: &str = "recipemd-search-index 1";
+
+/// The indexed fields of a recipe and their weight for ranking.
+const FIELDS: [(Field, f32); 5] = [
+    (Field::Title, 5.0),
+    (Field::Tags, 3.0),
+    (Field::Ingredients, 2.0),
+    (Field::Description, 1.0),
+    (Field::Instructions, 1.0),
+];
+
+#[derive(Clone, Copy)]
+enum Field {
+    Title,
+    Tags,
+    Ingredients,
+    Description,
+    Instructions,
+}
+
+// BM25 parameters
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// The language used for stemming words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    fn name(self) -> &'static str {
+        match self {
+            Language::English => "english",
+            Language::German => "german",
+        }
+    }
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "english" => Some(Language::English),
+            "german" => Some(Language::German),
+            _ => None,
+        }
+    }
+    fn stemmer(self) -> Stemmer {
+        Stemmer::create(match self {
+            Language::English => Algorithm::English,
+            Language::German => Algorithm::German,
+        })
+    }
+}
+
+/// A search result.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit<'i> {
+    pub path: &'i Path,
+    pub title: &'i str,
+    pub score: f32,
+}
+
+/// An inverted index over the title, tags, ingredient names, description and instructions of
+/// recipes.
+///
+/// Words are stemmed, so searching for "baking" also finds "bake" and "baked". Results are
+/// ranked using BM25, where matches in the title weigh more than matches in the tags, which
+/// weigh more than matches in the ingredients, description or instructions.
+///
+/// The index can be updated incrementally when a single recipe changes and can be saved to a
+/// file to avoid reindexing a whole collection.
+///
+/// ```
+/// # use recipemd::{Recipe, search::{Language, SearchIndex}};
+/// # fn main() -> recipemd::Result<()> {
+/// let mut index = SearchIndex::new(Language::English);
+/// index.insert("bread.md", &Recipe::parse("# Bread\n\n---\n\n- flour\n\n---\n\nBake it.")?);
+/// index.insert("cake.md", &Recipe::parse("# Baked Cake\n\n---\n\n- flour\n- sugar\n")?);
+///
+/// let hits = index.search("baking");
+/// assert_eq!(hits[0].title, "Baked Cake");
+/// assert_eq!(hits[1].title, "Bread");
+/// # Ok(()) }
+/// ```
+pub struct SearchIndex {
+    language: Language,
+    stemmer: Stemmer,
+    canonicalizer: Canonicalizer,
+    documents: BTreeMap<PathBuf, Document>,
+    postings: HashMap<String, HashMap<PathBuf, [u32; FIELDS.len()]>>,
+    total_lengths: [u64; FIELDS.len()],
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Document {
+    title: String,
+    lengths: [u32; FIELDS.len()],
+    terms: BTreeMap<String, [u32; FIELDS.len()]>,
+}
+
+impl std::fmt::Debug for SearchIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchIndex")
+            .field("language", &self.language)
+            .field("documents", &self.documents.len())
+            .field("terms", &self.postings.len())
+            .finish()
+    }
+}
+
+impl SearchIndex {
+    /// Creates an empty index.
+    pub fn new(language: Language) -> Self {
+        Self {
+            language,
+            stemmer: language.stemmer(),
+            canonicalizer: Canonicalizer::default(),
+            documents: BTreeMap::new(),
+            postings: HashMap::new(),
+            total_lengths: [0; FIELDS.len()],
+        }
+    }
+
+    /// Sets the canonicalizer that is used for matching ingredient names.
+    ///
+    /// Ingredients are indexed by both their name and their canonical name, so searching for a
+    /// synonym also finds the ingredient. Recipes that were inserted before aren't updated.
+    pub fn with_canonicalizer(mut self, canonicalizer: Canonicalizer) -> Self {
+        self.canonicalizer = canonicalizer;
+        self
+    }
+
+    /// Creates an index containing all recipes of a collection.
+    pub fn from_collection(collection: &Collection, language: Language) -> Self {
+        let mut index = Self::new(language);
+        for entry in collection {
+            index.insert(&entry.path, &entry.recipe);
+        }
+        index
+    }
+
+    /// Returns the number of indexed recipes.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns `true` if the index doesn't contain any recipes.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Adds a recipe to the index, replacing any recipe with the same path.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, recipe: &Recipe) {
+        let path = path.into();
+        let mut document = Document {
+            title: recipe.title.clone(),
+            ..Default::default()
+        };
+
+        for (i, (field, _)) in FIELDS.iter().enumerate() {
+            let text = match field {
+                Field::Title => strip_markdown(&recipe.title),
+                Field::Tags => recipe.tags.join(" "),
+                Field::Ingredients => recipe
+                    .all_ingredients()
+                    .map(|ingredient| {
+                        let name = strip_markdown(&ingredient.name);
+                        let canonical = self.canonicalizer.canonicalize(&name);
+                        format!("{name} {canonical}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                Field::Description => recipe
+                    .description
+                    .as_deref()
+                    .map(strip_markdown)
+                    .unwrap_or_default(),
+                Field::Instructions => recipe
+                    .instructions
+                    .as_deref()
+                    .map(strip_markdown)
+                    .unwrap_or_default(),
+            };
+            for term in self.tokenize(&text) {
+                document.lengths[i] += 1;
+                document.terms.entry(term).or_default()[i] += 1;
+            }
+        }
+
+        self.insert_document(path, document);
+    }
+
+    /// Removes the recipe with the given path from the index.
+    ///
+    /// Returns `true` if the recipe was part of the index.
+    pub fn remove(&mut self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        let Some(document) = self.documents.remove(path) else {
+            return false;
+        };
+
+        for (total, length) in self.total_lengths.iter_mut().zip(document.lengths) {
+            *total -= length as u64;
+        }
+        for term in document.terms.keys() {
+            if let Some(postings) = self.postings.get_mut(term) {
+                postings.remove(path);
+                if postings.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Searches for recipes that contain any of the words in the query, best matches first.
+    pub fn search(&self, query: &str) -> Vec<SearchHit<'_>> {
+        let n = self.documents.len() as f32;
+        let average_lengths = self
+            .total_lengths
+            .map(|total| (total as f32 / n.max(1.0)).max(1.0));
+
+        // also search for the canonical names of the query and its words to find synonyms
+        let mut terms = self.tokenize(query);
+        terms.extend(self.tokenize(&self.canonicalizer.canonicalize(query)));
+        for word in query.split_whitespace() {
+            terms.extend(self.tokenize(&self.canonicalizer.canonicalize(word)));
+        }
+        terms.sort();
+        terms.dedup();
+
+        let mut scores: HashMap<&Path, f32> = HashMap::new();
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let df = postings.len() as f32;
+            let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+
+            for (path, frequencies) in postings {
+                let document = &self.documents[path];
+                let tf: f32 = FIELDS
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (_, weight))| {
+                        let normalization =
+                            1.0 - B + B * document.lengths[i] as f32 / average_lengths[i];
+                        weight * frequencies[i] as f32 / normalization
+                    })
+                    .sum();
+                *scores.entry(path).or_default() += idf * tf * (K1 + 1.0) / (tf + K1);
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .map(|(path, score)| SearchHit {
+                path,
+                title: &self.documents[path].title,
+                score,
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.path.cmp(b.path)));
+        hits
+    }
+
+    /// Saves the index to a file, together with the canonicalizer it uses.
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] if the path of a recipe isn't valid UTF-8.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let paths = self
+            .documents
+            .keys()
+            .map(|path| {
+                path.to_str().ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("path {path:?} is not valid UTF-8"),
+                    )
+                })
+            })
+            .collect::<std::io::Result<Vec<&str>>>()?;
+        let mut w = BufWriter::new(std::fs::File::create(path)?);
+
+        writeln!(w, "{FORMAT_HEADER} {}", self.language.name())?;
+        let mut preparation_words: Vec<&String> =
+            self.canonicalizer.preparation_words.iter().collect();
+        preparation_words.sort();
+        for word in preparation_words {
+            writeln!(w, "P\t{}", escape(word))?;
+        }
+        let synonyms: BTreeMap<&String, &String> = self.canonicalizer.synonyms.iter().collect();
+        for (synonym, canonical) in synonyms {
+            writeln!(w, "S\t{}\t{}", escape(synonym), escape(canonical))?;
+        }
+        for (path, document) in paths.into_iter().zip(self.documents.values()) {
+            writeln!(
+                w,
+                "D\t{}\t{}\t{}",
+                escape(path),
+                escape(&document.title),
+                join(&document.lengths)
+            )?;
+            for (term, frequencies) in &document.terms {
+                writeln!(w, "T\t{term}\t{}", join(frequencies))?;
+            }
+        }
+
+        w.flush()
+    }
+
+    /// Loads an index that was previously saved using [`SearchIndex::save`].
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid index file");
+        let mut lines = std::io::BufReader::new(std::fs::File::open(path)?).lines();
+
+        let header = lines.next().ok_or_else(invalid)??;
+        let language = header
+            .strip_prefix(FORMAT_HEADER)
+            .and_then(|language| Language::from_name(language.trim()))
+            .ok_or_else(invalid)?;
+        let mut index = Self::new(language);
+        index.canonicalizer.preparation_words.clear();
+
+        let mut current: Option<(PathBuf, Document)> = None;
+        for line in lines {
+            let line = line?;
+            let mut parts = line.split('\t');
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some("P"), Some(word), None, None) if current.is_none() => {
+                    index.canonicalizer.preparation_words.insert(unescape(word));
+                }
+                (Some("S"), Some(synonym), Some(canonical), None) if current.is_none() => {
+                    index
+                        .canonicalizer
+                        .synonyms
+                        .insert(unescape(synonym), unescape(canonical));
+                }
+                (Some("D"), Some(path), Some(title), Some(lengths)) => {
+                    if let Some((path, document)) = current.take() {
+                        index.insert_document(path, document);
+                    }
+                    let document = Document {
+                        title: unescape(title),
+                        lengths: split(lengths).ok_or_else(invalid)?,
+                        terms: BTreeMap::new(),
+                    };
+                    current = Some((PathBuf::from(unescape(path)), document));
+                }
+                (Some("T"), Some(term), Some(frequencies), None) => {
+                    let (_, document) = current.as_mut().ok_or_else(invalid)?;
+                    document
+                        .terms
+                        .insert(term.to_owned(), split(frequencies).ok_or_else(invalid)?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if let Some((path, document)) = current.take() {
+            index.insert_document(path, document);
+        }
+
+        Ok(index)
+    }
+
+    fn insert_document(&mut self, path: PathBuf, document: Document) {
+        self.remove(&path);
+
+        for (total, length) in self.total_lengths.iter_mut().zip(document.lengths) {
+            *total += length as u64;
+        }
+        for (term, frequencies) in &document.terms {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .insert(path.clone(), *frequencies);
+        }
+        self.documents.insert(path, document);
+    }
+
+    /// Splits a text into lowercase, stemmed words.
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| self.stemmer.stem(&word.to_lowercase()).into_owned())
+            .collect()
+    }
+}
+
+fn join(values: &[u32]) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn split(s: &str) -> Option<[u32; FIELDS.len()]> {
+    let values = s
+        .split(',')
+        .map(|v| v.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    values.try_into().ok()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\' && !chars.as_str().is_empty()) {
+            ('\\', true) => match chars.next() {
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => (),
+            },
+            (c, _) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn recipe(src: &str) -> Recipe {
+        Recipe::parse(src).unwrap()
+    }
+
+    #[test]
+    fn search_and_update() {
+        let mut index = SearchIndex::new(Language::German);
+        index.insert(
+            "kuchen.md",
+            &recipe("# Apfelkuchen\n\n*backen*\n\n---\n\n- *3* Äpfel\n- Mehl\n"),
+        );
+        index.insert(
+            "brot.md",
+            &recipe("# Brot\n\n---\n\n- Mehl\n\n---\n\nDas Brot backen."),
+        );
+
+        let titles =
+            |hits: Vec<SearchHit>| hits.iter().map(|h| h.title.to_owned()).collect::<Vec<_>>();
+        assert_eq!(titles(index.search("Backen")), vec!["Apfelkuchen", "Brot"]);
+        assert_eq!(titles(index.search("äpfel")), vec!["Apfelkuchen"]);
+
+        index.insert("brot.md", &recipe("# Brot\n\n---\n\n- Roggenmehl\n"));
+        assert_eq!(titles(index.search("backen")), vec!["Apfelkuchen"]);
+        assert!(index.remove("kuchen.md"));
+        assert!(index.search("backen").is_empty());
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn search_synonyms() {
+        let canonicalizer = Canonicalizer::parse("## tomato\n\n- Tomaten\n").unwrap();
+        let mut index = SearchIndex::new(Language::English).with_canonicalizer(canonicalizer);
+        index.insert(
+            "salad.md",
+            &recipe("# Salad\n\n---\n\n- *3* ripe tomatoes\n"),
+        );
+
+        assert_eq!(index.search("Tomaten").len(), 1);
+        assert_eq!(index.search("tomato").len(), 1);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut index = SearchIndex::new(Language::English);
+        index.insert(
+            "tabs\tand\nnewlines.md",
+            &recipe("# Pasta\n\n*italian*\n\n---\n\n- *200 g* spaghetti\n"),
+        );
+
+        let file = std::env::temp_dir().join(format!("recipemd-index-{}", std::process::id()));
+        index.save(&file).unwrap();
+        let loaded = SearchIndex::load(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(loaded.language, Language::English);
+        assert_eq!(loaded.documents, index.documents);
+        assert_eq!(loaded.postings, index.postings);
+        assert_eq!(loaded.total_lengths, index.total_lengths);
+        assert_eq!(
+            loaded.search("spaghetti")[0].path,
+            Path::new("tabs\tand\nnewlines.md")
+        );
+    }
+
+    #[test]
+    fn save_and_load_canonicalizer() {
+        let mut canonicalizer = Canonicalizer::parse("## tomato\n\n- Paradeiser\n").unwrap();
+        canonicalizer.add_preparation_word("sonnengereift");
+        let mut index = SearchIndex::new(Language::German).with_canonicalizer(canonicalizer);
+        index.insert("salat.md", &recipe("# Salat\n\n---\n\n- *3* Tomaten\n"));
+
+        let file = std::env::temp_dir().join(format!(
+            "recipemd-index-canonicalizer-{}",
+            std::process::id()
+        ));
+        index.save(&file).unwrap();
+        let loaded = SearchIndex::load(&file).unwrap();
+        std::fs::remove_file(&file).unwrap();
+
+        assert_eq!(
+            loaded.canonicalizer.preparation_words,
+            index.canonicalizer.preparation_words
+        );
+        assert_eq!(loaded.canonicalizer.synonyms, index.canonicalizer.synonyms);
+        assert_eq!(
+            loaded
+                .canonicalizer
+                .canonicalize("sonnengereift Paradeiser"),
+            "tomato"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn save_non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut index = SearchIndex::new(Language::English);
+        index.insert(
+            std::ffi::OsStr::from_bytes(b"invalid-\xff.md"),
+            &recipe("# Pasta\n\n---\n"),
+        );
+
+        let file = std::env::temp_dir().join(format!("recipemd-index-utf8-{}", std::process::id()));
+        let error = index.save(&file).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(!file.exists());
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Detect and convert temperatures like "180 °C", "350F" or "gas mark 4".
+
+use std::ops::Range;
+
+use lazy_regex::regex;
+
+use crate::Recipe;
+
+/// A unit of temperature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    /// The gas mark scale used for ovens in the UK.
+    GasMark,
+}
+
+/// A temperature or temperature range that was found in a text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemperatureMention {
+    pub value: f64,
+    /// The upper end of a range like "175–180 °C".
+    pub max: Option<f64>,
+    pub unit: TemperatureUnit,
+    /// The byte range of the temperature within the text.
+    pub span: Range<usize>,
+}
+
+/// The text field of a [`Recipe`] a [`TemperatureMention`] was found in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextField {
+    Description,
+    Instructions,
+}
+
+impl TemperatureMention {
+    /// Converts the temperature to a different unit.
+    ///
+    /// Celsius and Fahrenheit values are rounded to multiples of 5, gas marks are rounded to
+    /// the nearest mark.
+    pub fn convert(&self, unit: TemperatureUnit) -> TemperatureMention {
+        let convert = |value| round(unit, to_unit(unit, to_celsius(self.unit, value)));
+
+        TemperatureMention {
+            value: convert(self.value),
+            max: self.max.map(convert),
+            unit,
+            span: self.span.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for TemperatureMention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = |value: f64| match (self.unit, value) {
+            (TemperatureUnit::GasMark, 0.25) => "¼".to_owned(),
+            (TemperatureUnit::GasMark, 0.5) => "½".to_owned(),
+            (_, value) => value.to_string(),
+        };
+        let range = match self.max {
+            Some(max) => format!("{}–{}", value(self.value), value(max)),
+            None => value(self.value),
+        };
+        match self.unit {
+            TemperatureUnit::Celsius => write!(f, "{range} °C"),
+            TemperatureUnit::Fahrenheit => write!(f, "{range} °F"),
+            TemperatureUnit::GasMark => write!(f, "gas mark {range}"),
+        }
+    }
+}
+
+impl Recipe {
+    /// Finds all temperatures in the description and instructions.
+    ///
+    /// See [`temperatures`] for details.
+    pub fn temperatures(&self) -> Vec<(TextField, TemperatureMention)> {
+        let description = self.description.as_deref().map(temperatures);
+        let instructions = self.instructions.as_deref().map(temperatures);
+
+        description
+            .into_iter()
+            .flatten()
+            .map(|m| (TextField::Description, m))
+            .chain(
+                instructions
+                    .into_iter()
+                    .flatten()
+                    .map(|m| (TextField::Instructions, m)),
+            )
+            .collect()
+    }
+
+    /// Rewrites all temperatures in the description and instructions to the given unit.
+    ///
+    /// If `keep_original` is `true`, the converted temperature is added in parentheses after the
+    /// original one instead of replacing it.
+    ///
+    /// ```
+    /// # use recipemd::{Recipe, temperatures::TemperatureUnit};
+    /// # fn main() -> recipemd::Result<()> {
+    /// let mut recipe = Recipe::parse("# Cake\n\n---\n\n---\n\nBake at 350F for 30 minutes.")?;
+    ///
+    /// recipe.convert_temperatures(TemperatureUnit::Celsius, true);
+    /// assert_eq!(
+    ///     recipe.instructions.as_deref(),
+    ///     Some("Bake at 350F (175 °C) for 30 minutes.")
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn convert_temperatures(&mut self, unit: TemperatureUnit, keep_original: bool) {
+        for text in [&mut self.description, &mut self.instructions]
+            .into_iter()
+            .flatten()
+        {
+            for mention in temperatures(text).into_iter().rev() {
+                if mention.unit == unit {
+                    continue;
+                }
+                let converted = mention.convert(unit).to_string();
+                match keep_original {
+                    true => text.insert_str(mention.span.end, &format!(" ({converted})")),
+                    false => text.replace_range(mention.span, &converted),
+                }
+            }
+        }
+    }
+}
+
+/// Finds all temperatures in a text.
+///
+/// Temperatures must either use a degree sign (`180 °C`, `350°F`), be spelled out
+/// (`180 degrees Celsius`, `180 Grad`) or directly follow the number (`350F`). Values in degrees
+/// without a unit are assumed to be Celsius if they are at most 300 and Fahrenheit otherwise.
+/// Gas marks are recognized as `gas mark 4` or `Gasstufe 4`.
+pub fn temperatures(src: &str) -> Vec<TemperatureMention> {
+    let mut mentions = Vec::new();
+
+    for m in regex!(
+        r"(?ix)
+        (?P<v1>\d+(?:[.,]\d+)?)
+        (?:\s*(?:-|–|—|to|bis)\s*(?P<v2>\d+(?:[.,]\d+)?))?
+        (?P<space>\s*)
+        (?P<degrees>°|º|degrees?\b|grad\b)?
+        (?:\s*(?P<unit>celsius|fahrenheit|c|f)\b)?"
+    )
+    .captures_iter(src)
+    {
+        let whole = m.get(0).expect("capture group 0 always exists");
+        if src[..whole.start()]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric)
+        {
+            continue;
+        }
+        let parse = |s: &str| s.replace(',', ".").parse::<f64>().ok();
+        let (Some(value), max) = (
+            parse(&m["v1"]),
+            m.name("v2").and_then(|v| parse(v.as_str())),
+        ) else {
+            continue;
+        };
+
+        let unit = match (m.name("degrees"), m.name("unit").map(|u| u.as_str())) {
+            (_, Some(u)) if u.len() > 1 && u[..1].eq_ignore_ascii_case("c") => {
+                TemperatureUnit::Celsius
+            }
+            (_, Some(u)) if u.len() > 1 => TemperatureUnit::Fahrenheit,
+            // a single letter without a degree sign might also be an abbreviation like "C" for cups
+            (None, Some(_)) if !m["space"].is_empty() && value < 100.0 => continue,
+            (_, Some("c" | "C")) => TemperatureUnit::Celsius,
+            (_, Some(_)) => TemperatureUnit::Fahrenheit,
+            (Some(_), None) if value <= 300.0 => TemperatureUnit::Celsius,
+            (Some(_), None) => TemperatureUnit::Fahrenheit,
+            (None, None) => continue,
+        };
+        let end = match m.name("unit").or(m.name("degrees")) {
+            Some(m) => m.end(),
+            None => whole.end(),
+        };
+
+        mentions.push(TemperatureMention {
+            value,
+            max,
+            unit,
+            span: whole.start()..end,
+        });
+    }
+
+    for m in regex!(
+        r"(?i)\b(?:gas\s+mark|gasstufe)\s+(?P<v1>\d+|¼|½)(?:\s*(?:-|–|—|to|bis)\s*(?P<v2>\d+))?"
+    )
+    .captures_iter(src)
+    {
+        let parse = |s: &str| match s {
+            "¼" => Some(0.25),
+            "½" => Some(0.5),
+            s => s.parse::<f64>().ok(),
+        };
+        let Some(value) = parse(&m["v1"]) else {
+            continue;
+        };
+        mentions.push(TemperatureMention {
+            value,
+            max: m.name("v2").and_then(|v| parse(v.as_str())),
+            unit: TemperatureUnit::GasMark,
+            span: m.get(0).expect("capture group 0 always exists").range(),
+        });
+    }
+
+    mentions.sort_by_key(|m| m.span.start);
+    mentions
+}
+
+fn to_celsius(unit: TemperatureUnit, value: f64) -> f64 {
+    match unit {
+        TemperatureUnit::Celsius => value,
+        TemperatureUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        TemperatureUnit::GasMark => {
+            let fahrenheit = match value {
+                v if v < 1.0 => 200.0 + 50.0 * v,
+                v => 250.0 + 25.0 * v,
+            };
+            to_celsius(TemperatureUnit::Fahrenheit, fahrenheit)
+        }
+    }
+}
+
+fn to_unit(unit: TemperatureUnit, celsius: f64) -> f64 {
+    match unit {
+        TemperatureUnit::Celsius => celsius,
+        TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        TemperatureUnit::GasMark => match to_unit(TemperatureUnit::Fahrenheit, celsius) {
+            f if f < 237.5 => 0.25,
+            f if f < 262.5 => 0.5,
+            f => ((f - 250.0) / 25.0).clamp(1.0, 10.0),
+        },
+    }
+}
+
+fn round(unit: TemperatureUnit, value: f64) -> f64 {
+    match unit {
+        TemperatureUnit::Celsius | TemperatureUnit::Fahrenheit => (value / 5.0).round() * 5.0,
+        TemperatureUnit::GasMark if value < 1.0 => value,
+        TemperatureUnit::GasMark => value.round(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn detect_temperatures() {
+        let src = "Preheat to 180 °C (gas mark 4). Heat oil to 350F, then 2 C flour. \
+                   Bei 160-170 Grad backen. Cool to 40 degrees Fahrenheit.";
+        let found: Vec<(f64, Option<f64>, TemperatureUnit, &str)> = temperatures(src)
+            .into_iter()
+            .map(|m| (m.value, m.max, m.unit, &src[m.span]))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (180.0, None, TemperatureUnit::Celsius, "180 °C"),
+                (4.0, None, TemperatureUnit::GasMark, "gas mark 4"),
+                (350.0, None, TemperatureUnit::Fahrenheit, "350F"),
+                (160.0, Some(170.0), TemperatureUnit::Celsius, "160-170 Grad"),
+                (
+                    40.0,
+                    None,
+                    TemperatureUnit::Fahrenheit,
+                    "40 degrees Fahrenheit"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn convert_temperatures() {
+        let mut recipe = Recipe::parse(
+            "# Bread\n\nBest baked at 220 °C.\n\n---\n\n---\n\nBake at gas mark 6 or 200–210 °C.",
+        )
+        .unwrap();
+        recipe.convert_temperatures(TemperatureUnit::Fahrenheit, false);
+
+        assert_eq!(recipe.description.as_deref(), Some("Best baked at 430 °F."));
+        assert_eq!(
+            recipe.instructions.as_deref(),
+            Some("Bake at 400 °F or 390–410 °F.")
+        );
+    }
+}
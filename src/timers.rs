@@ -0,0 +1,339 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Extract durations like "bake for 25–30 minutes" from a recipe's instructions.
+
+use std::{ops::Range, time::Duration};
+
+use lazy_regex::{regex, Lazy, Regex};
+
+use crate::{utils::decode_unicode_fraction, Recipe};
+
+const MINUTE: f64 = 60.0;
+const HOUR: f64 = 60.0 * MINUTE;
+const DAY: f64 = 24.0 * HOUR;
+
+/// A range between a minimum and maximum duration.
+///
+/// For exact durations like "10 minutes", `min` and `max` are equal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DurationRange {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl std::ops::Add for DurationRange {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        DurationRange {
+            min: self.min.saturating_add(rhs.min),
+            max: self.max.saturating_add(rhs.max),
+        }
+    }
+}
+
+/// Whether the cook is busy during a period of time or can do something else.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeKind {
+    /// e.g. "knead for 10 minutes"
+    Active,
+    /// e.g. "bake for 25 minutes" or "let rest overnight"
+    Passive,
+}
+
+/// A duration that was found in the instructions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DurationMention {
+    pub duration: DurationRange,
+    pub kind: TimeKind,
+    /// The byte range of the duration within the instructions.
+    pub span: Range<usize>,
+}
+
+/// The estimated time needed to prepare a recipe.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeEstimate {
+    pub active: DurationRange,
+    pub passive: DurationRange,
+}
+
+impl TimeEstimate {
+    /// Returns the sum of active and passive time.
+    pub fn total(&self) -> DurationRange {
+        self.active + self.passive
+    }
+}
+
+impl Recipe {
+    /// Finds all durations in the instructions.
+    ///
+    /// See [`durations`] for details.
+    pub fn durations(&self) -> Vec<DurationMention> {
+        self.instructions
+            .as_deref()
+            .map(durations)
+            .unwrap_or_default()
+    }
+
+    /// Estimates the total active and passive time by summing up all durations in the
+    /// instructions.
+    pub fn time_estimate(&self) -> TimeEstimate {
+        self.durations()
+            .into_iter()
+            .fold(TimeEstimate::default(), |mut estimate, mention| {
+                match mention.kind {
+                    TimeKind::Active => estimate.active = estimate.active + mention.duration,
+                    TimeKind::Passive => estimate.passive = estimate.passive + mention.duration,
+                }
+                estimate
+            })
+    }
+}
+
+/// Finds all durations in a text.
+///
+/// English and German units from seconds to days are recognized, as well as ranges
+/// ("25–30 minutes", "2 to 3 hours"), compound durations ("1 h 15 min") and "overnight"
+/// (8 to 12 hours). Numbers that are written as words ("a", "two") must be followed by a unit
+/// that is spelled out ("a minute", but not "a min"). A duration is considered [passive](TimeKind::Passive) if the sentence it
+/// appears in contains a verb like "bake", "rest" or "chill".
+///
+/// ```
+/// # use std::time::Duration;
+/// # use recipemd::timers::{durations, TimeKind};
+/// let durations = durations("Knead for 10 min. Bake for 25–30 minutes.");
+///
+/// assert_eq!(durations[0].duration.min, Duration::from_secs(10 * 60));
+/// assert_eq!(durations[0].kind, TimeKind::Active);
+/// assert_eq!(durations[1].duration.max, Duration::from_secs(30 * 60));
+/// assert_eq!(durations[1].kind, TimeKind::Passive);
+/// assert_eq!(durations[1].span, 27..42);
+/// ```
+pub fn durations(src: &str) -> Vec<DurationMention> {
+    let mut components: Vec<(Range<usize>, f64, f64, f64)> = Vec::new();
+
+    for m in DURATION_REGEX.captures_iter(src) {
+        let whole = m.get(0).expect("capture group 0 always exists");
+        let preceded_by_word = src[..whole.start()]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        // words like "as" or "ah" would otherwise be read as "a s" or "a h"
+        let is_word_with_abbreviation = m["n1"].starts_with(char::is_alphabetic)
+            && regex!(r"(?i)^(?:secs?|s|mins?|hrs?|h|std)$").is_match(&m["unit"]);
+        if preceded_by_word || is_word_with_abbreviation {
+            continue;
+        }
+        let Some(n1) = parse_number(&m["n1"]) else {
+            continue;
+        };
+        let n2 = m.name("n2").and_then(|n2| parse_number(n2.as_str()));
+        let unit = match m["unit"].to_lowercase().as_str() {
+            u if u.starts_with('s') && !u.starts_with("std") && !u.starts_with("stu") => 1.0,
+            u if u.starts_with('m') => MINUTE,
+            u if u.starts_with('h') || u.starts_with("st") => HOUR,
+            _ => DAY,
+        };
+        let (min, max) = match n2 {
+            Some(n2) => (n1.min(n2), n1.max(n2)),
+            None => (n1, n1),
+        };
+        components.push((whole.range(), min * unit, max * unit, unit));
+    }
+
+    let mut mentions: Vec<DurationMention> = Vec::new();
+    let mut previous_unit = f64::INFINITY;
+
+    for (span, min, max, unit) in components {
+        // skip numbers like `1/0` or durations that are too long to be represented
+        let (Ok(min), Ok(max)) = (
+            Duration::try_from_secs_f64(min),
+            Duration::try_from_secs_f64(max),
+        ) else {
+            continue;
+        };
+        let duration = DurationRange { min, max };
+        match mentions.last_mut() {
+            // compound durations like "1 h 15 min" or "1 hour and 15 minutes"
+            Some(last)
+                if unit < previous_unit
+                    && regex!(r"(?i)^\s*(?:,|and|und)?\s*$")
+                        .is_match(&src[last.span.end..span.start]) =>
+            {
+                last.duration = last.duration + duration;
+                last.span.end = span.end;
+            }
+            _ => mentions.push(DurationMention {
+                duration,
+                kind: TimeKind::Active,
+                span,
+            }),
+        }
+        previous_unit = unit;
+    }
+
+    for m in regex!(r"(?i)\b(?:overnight|über\s+nacht)\b").find_iter(src) {
+        mentions.push(DurationMention {
+            duration: DurationRange {
+                min: Duration::from_secs_f64(8.0 * HOUR),
+                max: Duration::from_secs_f64(12.0 * HOUR),
+            },
+            kind: TimeKind::Passive,
+            span: m.range(),
+        });
+    }
+    mentions.sort_by_key(|m| m.span.start);
+
+    for mention in &mut mentions {
+        if is_passive(sentence(src, &mention.span)) {
+            mention.kind = TimeKind::Passive;
+        }
+    }
+
+    mentions
+}
+
+static DURATION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(&format!(
+        r"(?ix)
+        (?P<n1>{NUMBER})
+        (?:\s*(?:-|–|—|to|or|bis|oder)\s*(?P<n2>{NUMBER}))?
+        \s*
+        (?P<unit>seconds?|secs?|s|sekunden?|minutes?|mins?|minuten?|hours?|hrs?|h|stunden?|std|days?|tagen?|tage)
+        \b"
+    ))
+    .expect("duration regex is valid")
+});
+
+const NUMBER: &str = r"\d+(?:[.,]\d+)?(?:\s*[¼-¾⅐-⅞]|\s*/\s*\d+)?|[¼-¾⅐-⅞]|half\s+an?|(?:eine\s+)?halbe|an?|one|two|three|four|five|six|ten|twelve|eine?|zwei|drei|vier|fünf|sechs|zehn|zwölf";
+
+fn parse_number(s: &str) -> Option<f64> {
+    let s = s.to_lowercase();
+    let value = match s.as_str() {
+        "a" | "an" | "one" | "ein" | "eine" => 1.0,
+        "two" | "zwei" => 2.0,
+        "three" | "drei" => 3.0,
+        "four" | "vier" => 4.0,
+        "five" | "fünf" => 5.0,
+        "six" | "sechs" => 6.0,
+        "ten" | "zehn" => 10.0,
+        "twelve" | "zwölf" => 12.0,
+        s if s.starts_with("half") || s.ends_with("halbe") => 0.5,
+        s => {
+            if let Some(m) = regex!(r"^(\d+)\s*/\s*(\d+)$").captures(s) {
+                return Some(m[1].parse::<f64>().ok()? / m[2].parse::<f64>().ok()?);
+            }
+            if let Some(m) = regex!(r"^(?:(\d+)\s*)?([¼-¾⅐-⅞])$").captures(s) {
+                let whole: f64 = m.get(1).map_or(Some(0.0), |m| m.as_str().parse().ok())?;
+                let (numerator, denominator) = decode_unicode_fraction(&m[2]);
+                return Some(whole + numerator as f64 / denominator as f64);
+            }
+            s.replace(',', ".").parse().ok()?
+        }
+    };
+    Some(value)
+}
+
+/// Returns the sentence that contains the given span.
+fn sentence<'s>(src: &'s str, span: &Range<usize>) -> &'s str {
+    let start = src[..span.start]
+        .rfind(['.', '!', '?', '\n'])
+        .map_or(0, |i| i + 1);
+    let end = src[span.end..]
+        .find(['.', '!', '?', '\n'])
+        .map_or(src.len(), |i| span.end + i);
+    &src[start..end]
+}
+
+fn is_passive(sentence: &str) -> bool {
+    regex!(
+        r"(?ix)\b(?:
+            bak(?:e|es|ed|ing)|roast|rest|chill|refrigerate|marinate|rise|prove|proof|soak|simmer
+            |cool|freeze|steep|ferment|braise|slow[\s-]cook|leave
+            |back(?:en|t)|ruhen|gehen|kühlen|abkühlen|ziehen|marinieren|quellen|köcheln|schmoren|garen
+        )"
+    )
+    .is_match(sentence)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn minutes(min: u64, max: u64) -> DurationRange {
+        DurationRange {
+            min: Duration::from_secs(min * 60),
+            max: Duration::from_secs(max * 60),
+        }
+    }
+
+    #[test]
+    fn parse_durations() {
+        let src = "Stir for 1 h 15 min. Let it rest for ½ hour and chill overnight. \
+                   Den Teig 2 bis 3 Stunden gehen lassen. Whisk for 30 s or a minute.";
+        let found: Vec<(DurationRange, TimeKind, &str)> = durations(src)
+            .into_iter()
+            .map(|m| (m.duration, m.kind, &src[m.span]))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (minutes(75, 75), TimeKind::Active, "1 h 15 min"),
+                (minutes(30, 30), TimeKind::Passive, "½ hour"),
+                (minutes(8 * 60, 12 * 60), TimeKind::Passive, "overnight"),
+                (minutes(120, 180), TimeKind::Passive, "2 bis 3 Stunden"),
+                (
+                    DurationRange {
+                        min: Duration::from_secs(30),
+                        max: Duration::from_secs(30)
+                    },
+                    TimeKind::Active,
+                    "30 s"
+                ),
+                (minutes(1, 1), TimeKind::Active, "a minute"),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_durations() {
+        assert_eq!(durations("Wait 1/0 min."), []);
+        assert_eq!(durations("Wait 99999999999999999999999 days."), []);
+
+        let mentions = durations("Wait 1/0 min, then 5 min.");
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].duration.min, Duration::from_secs(5 * 60));
+    }
+
+    #[test]
+    fn words_that_are_not_durations() {
+        assert_eq!(durations("Stir as needed, ah yes."), []);
+        assert_eq!(durations("Bake as long as you like."), []);
+        assert_eq!(durations("Label it a s and b h."), []);
+
+        let mentions = durations("Rest for an hour or a min.");
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].duration, minutes(60, 60));
+    }
+
+    #[test]
+    fn estimate_time() {
+        let recipe = Recipe::parse(
+            "# Bread\n\n---\n\n- flour\n\n---\n\nKnead for 10 minutes.\n\nBake for 40–50 minutes.\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipe.time_estimate(),
+            TimeEstimate {
+                active: minutes(10, 10),
+                passive: minutes(40, 50),
+            }
+        );
+        assert_eq!(recipe.time_estimate().total(), minutes(50, 60));
+    }
+}
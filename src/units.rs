@@ -0,0 +1,212 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Convert amounts between common units of mass and volume.
+
+use crate::{utils::word_forms, Amount, Factor};
+
+/// The physical quantity a [`Unit`] measures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    Mass,
+    Volume,
+}
+
+/// A known unit of measurement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Unit {
+    /// The canonical abbreviation of the unit, e.g. `g` or `tbsp`.
+    pub symbol: &'static str,
+    pub dimension: Dimension,
+    /// The size of the unit in grams or milliliters.
+    pub size: f32,
+}
+
+impl Unit {
+    /// Looks up a unit by its name or abbreviation, ignoring case and a trailing period.
+    ///
+    /// English and German names are recognized.
+    pub fn lookup(name: &str) -> Option<Unit> {
+        let name = name.trim().trim_end_matches('.').to_lowercase();
+        UNITS
+            .iter()
+            .find(|(_, aliases)| aliases.contains(&name.as_str()))
+            .map(|(unit, _)| *unit)
+    }
+
+    /// Returns all known units.
+    pub fn all() -> impl Iterator<Item = Unit> {
+        UNITS.iter().map(|(unit, _)| *unit)
+    }
+}
+
+const fn unit(symbol: &'static str, dimension: Dimension, size: f32) -> Unit {
+    Unit {
+        symbol,
+        dimension,
+        size,
+    }
+}
+
+const UNITS: &[(Unit, &[&str])] = &[
+    (
+        unit("mg", Dimension::Mass, 0.001),
+        &["mg", "milligram", "milligrams", "milligramm"],
+    ),
+    (
+        unit("g", Dimension::Mass, 1.0),
+        &["g", "gr", "gram", "grams", "gramm"],
+    ),
+    (
+        unit("kg", Dimension::Mass, 1000.0),
+        &["kg", "kilo", "kilos", "kilogram", "kilograms", "kilogramm"],
+    ),
+    (
+        unit("oz", Dimension::Mass, 28.349_523),
+        &["oz", "ounce", "ounces", "unze", "unzen"],
+    ),
+    (
+        unit("lb", Dimension::Mass, 453.592_37),
+        &["lb", "lbs", "pound", "pounds", "pfund"],
+    ),
+    (
+        unit("ml", Dimension::Volume, 1.0),
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+    ),
+    (
+        unit("cl", Dimension::Volume, 10.0),
+        &[
+            "cl",
+            "centiliter",
+            "centiliters",
+            "centilitre",
+            "centilitres",
+        ],
+    ),
+    (
+        unit("dl", Dimension::Volume, 100.0),
+        &["dl", "deciliter", "deciliters", "decilitre", "decilitres"],
+    ),
+    (
+        unit("l", Dimension::Volume, 1000.0),
+        &["l", "liter", "liters", "litre", "litres"],
+    ),
+    (
+        unit("tsp", Dimension::Volume, 5.0),
+        &["tsp", "teaspoon", "teaspoons", "tl", "teelöffel"],
+    ),
+    (
+        unit("tbsp", Dimension::Volume, 15.0),
+        &[
+            "tbsp",
+            "tbs",
+            "tablespoon",
+            "tablespoons",
+            "el",
+            "esslöffel",
+        ],
+    ),
+    (
+        unit("fl oz", Dimension::Volume, 29.573_53),
+        &["fl oz", "fl. oz", "fluid ounce", "fluid ounces"],
+    ),
+    (
+        unit("cup", Dimension::Volume, 236.588_24),
+        &["cup", "cups", "c"],
+    ),
+    (
+        unit("pt", Dimension::Volume, 473.176_47),
+        &["pt", "pint", "pints"],
+    ),
+    (
+        unit("qt", Dimension::Volume, 946.352_9),
+        &["qt", "quart", "quarts"],
+    ),
+    (
+        unit("gal", Dimension::Volume, 3_785.411_8),
+        &["gal", "gallon", "gallons"],
+    ),
+];
+
+/// Converts a value from one unit to another.
+///
+/// Returns `None` if one of the units is unknown or if they measure different dimensions.
+///
+/// ```
+/// # use recipemd::units::convert;
+/// assert_eq!(convert(1.5, "kg", "g"), Some(1500.0));
+/// assert_eq!(convert(2.0, "EL", "tsp"), Some(6.0));
+/// assert_eq!(convert(1.0, "cup", "g"), None);
+/// ```
+pub fn convert(value: f32, from: &str, to: &str) -> Option<f32> {
+    let (from, to) = (Unit::lookup(from)?, Unit::lookup(to)?);
+    (from.dimension == to.dimension).then(|| value * from.size / to.size)
+}
+
+impl Amount {
+    /// Converts the amount to a different unit.
+    ///
+    /// Amounts without a unit can only be "converted" to no unit. Amounts with a unit that is
+    /// not known to [`Unit::lookup`] can only be converted to the same unit, ignoring case and
+    /// plural forms like "clove" and "cloves".
+    pub fn convert_to(&self, unit: Option<&str>) -> Option<Amount> {
+        let value = f32::from(self.factor);
+        let converted = match (self.unit.as_deref(), unit) {
+            (None, None) => value,
+            (Some(from), Some(to))
+                if word_forms(&from.to_lowercase()).contains(&to.to_lowercase()) =>
+            {
+                value
+            }
+            (Some(from), Some(to)) => convert(value, from, to)?,
+            _ => return None,
+        };
+
+        Some(Amount {
+            factor: match self.factor {
+                Factor::Integer(_) | Factor::Fraction(..) if converted == value => self.factor,
+                _ => Factor::Float(converted),
+            },
+            unit: unit.map(ToOwned::to_owned),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn convert_amounts() {
+        let amount = |factor, unit: Option<&str>| Amount {
+            factor,
+            unit: unit.map(ToOwned::to_owned),
+        };
+
+        assert_eq!(
+            amount(Factor::Fraction(1, 2), Some("l")).convert_to(Some("ml")),
+            Some(amount(Factor::Float(500.0), Some("ml")))
+        );
+        assert_eq!(
+            amount(Factor::Integer(3), Some("Cloves")).convert_to(Some("clove")),
+            Some(amount(Factor::Integer(3), Some("clove")))
+        );
+        assert_eq!(
+            amount(Factor::Integer(3), None).convert_to(None),
+            Some(amount(Factor::Integer(3), None))
+        );
+        assert_eq!(amount(Factor::Integer(3), Some("g")).convert_to(None), None);
+        assert_eq!(
+            amount(Factor::Integer(1), Some("lb")).convert_to(Some("oz")),
+            Some(amount(Factor::Float(16.0), Some("oz")))
+        );
+    }
+}
@@ -26,6 +26,61 @@ pub(crate) fn escape_url(s: impl AsRef<str>) -> String {
     buf
 }
 
+/// Strips all markup from a markdown string and returns its plain text.
+///
+/// Blocks are separated by empty lines, soft line breaks are replaced by spaces.
+pub(crate) fn strip_markdown(src: &str) -> String {
+    use pulldown_cmark::{Event, Parser, TagEnd};
+
+    fn line_break(text: &mut String, newlines: usize) {
+        let existing = text.len() - text.trim_end_matches('\n').len();
+        if !text.is_empty() && existing < newlines {
+            text.extend(std::iter::repeat_n('\n', newlines - existing));
+        }
+    }
+
+    let mut text = String::new();
+    for event in Parser::new(src) {
+        match event {
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak | Event::End(TagEnd::Item) => line_break(&mut text, 1),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                line_break(&mut text, 2)
+            }
+            _ => (),
+        }
+    }
+    text.trim_end().to_owned()
+}
+
+/// Returns the lowercase name of an ingredient without markup and without additions like
+/// "flour, sifted" or "butter (soft)".
+pub(crate) fn base_name(name: &str) -> String {
+    let name = strip_markdown(name).to_lowercase();
+    name.split([',', '('])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_owned()
+}
+
+/// Returns a word together with its likely singular or plural forms.
+pub(crate) fn word_forms(word: &str) -> Vec<String> {
+    let mut forms = vec![word.to_owned()];
+    if let Some(singular) = word.strip_suffix("es") {
+        forms.push(singular.to_owned());
+    }
+    match word.strip_suffix('s') {
+        Some(singular) => forms.push(singular.to_owned()),
+        None => {
+            forms.push(format!("{word}s"));
+            forms.push(format!("{word}es"));
+        }
+    }
+    forms
+}
+
 pub(crate) trait TrimNewlines {
     fn trim_newlines(&self) -> &Self;
 }
@@ -0,0 +1,341 @@
+// Copyright (c) 2023 d-k-bo
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! Variants of a recipe that differ in a few ingredients.
+//!
+//! This is not part of the RecipeMD specification. Variants are written as ingredient groups
+//! whose title starts with `Variant:` (or `Variante:`), followed by the name of the variant.
+//! Each item of such a group changes the ingredients of the recipe:
+//!
+//! - `*80 ml* vegetable oil instead of butter` (or `statt`) replaces the ingredient `butter`,
+//! - `without eggs` (or `ohne`) removes the ingredient `eggs`,
+//! - any other item is added to the ingredients.
+//!
+//! Ingredients are matched by their name, ignoring case. The groups are only recognized if the
+//! [`VariantExtension`] is registered, otherwise they are parsed as ordinary ingredient groups.
+//!
+//! ```
+//! # use recipemd::{variants::VariantExtension, ParseOptions, Recipe};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let src = "# Pancakes\n\n---\n\n- *250 ml* milk\n- *2* eggs\n\n\
+//!     ## Variant: vegan\n\n- *250 ml* oat milk instead of milk\n- without eggs\n\
+//!     - *1 tbsp* ground flaxseed\n";
+//! let options = ParseOptions::new().extension(VariantExtension);
+//! let recipe = Recipe::parse_with_options(src, &options)?;
+//!
+//! assert!(recipe.ingredient_groups.is_empty());
+//! assert_eq!(recipe.variants().collect::<Vec<_>>(), ["vegan"]);
+//!
+//! let vegan = recipe.variant("vegan")?;
+//! let names: Vec<&str> = vegan.ingredients.iter().map(|i| i.name.as_str()).collect();
+//! assert_eq!(names, ["oat milk", "ground flaxseed"]);
+//! # Ok(()) }
+//! ```
+
+use lazy_regex::regex;
+
+use crate::{
+    extension::{Extension, Extensions},
+    Ingredient, IngredientGroup, Recipe,
+};
+
+/// An [`Extension`] that parses variant groups into [`Variants`], see the
+/// [module documentation](self).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VariantExtension;
+
+impl Extension for VariantExtension {
+    fn parse_ingredient_group(
+        &self,
+        _src: &str,
+        group: &IngredientGroup,
+        data: &mut Extensions,
+    ) -> Result<bool, String> {
+        let Some(name) = regex!(r"(?i)^variante?:\s*(.*)$")
+            .captures(&group.title)
+            .map(|c| c[1].trim().to_owned())
+        else {
+            return Ok(false);
+        };
+        if name.is_empty() {
+            return Err("variant is missing a name".to_owned());
+        }
+        if !group.ingredient_groups.is_empty() {
+            return Err(format!("variant `{name}` contains ingredient groups"));
+        }
+
+        let changes = group.ingredients.iter().map(parse_change);
+        let variants = &mut data.get_or_default::<Variants>().0;
+        match variants
+            .iter_mut()
+            .find(|variant| variant.name.to_lowercase() == name.to_lowercase())
+        {
+            Some(variant) => variant.changes.extend(changes),
+            None => variants.push(Variant {
+                name,
+                changes: changes.collect(),
+            }),
+        }
+        Ok(true)
+    }
+}
+
+/// The variants of a recipe, stored in [`Recipe::extensions`] by the [`VariantExtension`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Variants(pub Vec<Variant>);
+
+/// A named variant of a recipe.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    pub name: String,
+    pub changes: Vec<Change>,
+}
+
+/// A change that a [`Variant`] makes to the ingredients of a recipe.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    /// Adds an ingredient after the top-level ingredients.
+    Add(Ingredient),
+    /// Replaces the ingredient with the given name.
+    Replace { name: String, with: Ingredient },
+    /// Removes the ingredient with the given name.
+    Remove(String),
+}
+
+/// Returned by [`Recipe::variant`].
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum VariantError {
+    #[error("variant `{0}` not found")]
+    VariantNotFound(String),
+    #[error("ingredient `{0}` not found")]
+    IngredientNotFound(String),
+}
+
+impl Recipe {
+    /// Returns the names of the variants of the recipe.
+    ///
+    /// Variants are only parsed if the [`VariantExtension`] is registered.
+    pub fn variants(&self) -> impl Iterator<Item = &str> {
+        self.extensions
+            .get::<Variants>()
+            .into_iter()
+            .flat_map(|variants| &variants.0)
+            .map(|variant| variant.name.as_str())
+    }
+
+    /// Returns the recipe with the changes of the variant with the given name applied.
+    ///
+    /// The name is matched ignoring case. The returned recipe has no variants.
+    pub fn variant(&self, name: &str) -> Result<Recipe, VariantError> {
+        let variant = self
+            .extensions
+            .get::<Variants>()
+            .and_then(|variants| {
+                variants
+                    .0
+                    .iter()
+                    .find(|variant| variant.name.to_lowercase() == name.to_lowercase())
+            })
+            .ok_or_else(|| VariantError::VariantNotFound(name.to_owned()))?;
+
+        let mut recipe = self.clone();
+        recipe.extensions.remove::<Variants>();
+        for change in &variant.changes {
+            match change {
+                Change::Add(ingredient) => recipe.ingredients.push(ingredient.clone()),
+                Change::Replace { name, with } => {
+                    *find_ingredient(&mut recipe, name)? = with.clone();
+                }
+                Change::Remove(name) => {
+                    if !remove_ingredient(
+                        &mut recipe.ingredients,
+                        &mut recipe.ingredient_groups,
+                        name,
+                    ) {
+                        return Err(VariantError::IngredientNotFound(name.to_owned()));
+                    }
+                }
+            }
+        }
+        Ok(recipe)
+    }
+}
+
+fn parse_change(ingredient: &Ingredient) -> Change {
+    let name = ingredient.name.trim();
+    if let Some(c) = regex!(r"(?is)^(?:without|ohne)\s+(.+)$").captures(name) {
+        if ingredient.amount.is_none() {
+            return Change::Remove(c[1].trim().to_owned());
+        }
+    }
+    if let Some(c) = regex!(r"(?is)^(.+?)\s+(?:instead of|statt)\s+(.+)$").captures(name) {
+        return Change::Replace {
+            name: c[2].trim().to_owned(),
+            with: Ingredient {
+                name: c[1].trim().to_owned(),
+                ..ingredient.clone()
+            },
+        };
+    }
+    Change::Add(ingredient.clone())
+}
+
+fn matches(ingredient: &Ingredient, name: &str) -> bool {
+    ingredient.name.trim().to_lowercase() == name.to_lowercase()
+}
+
+/// Returns the first ingredient with the given name, including those in ingredient groups.
+fn find_ingredient<'a>(
+    recipe: &'a mut Recipe,
+    name: &str,
+) -> Result<&'a mut Ingredient, VariantError> {
+    fn find_in_groups<'a>(
+        groups: &'a mut [IngredientGroup],
+        name: &str,
+    ) -> Option<&'a mut Ingredient> {
+        groups.iter_mut().find_map(|group| {
+            match group.ingredients.iter_mut().find(|i| matches(i, name)) {
+                Some(ingredient) => Some(ingredient),
+                None => find_in_groups(&mut group.ingredient_groups, name),
+            }
+        })
+    }
+
+    match recipe.ingredients.iter_mut().find(|i| matches(i, name)) {
+        Some(ingredient) => Some(ingredient),
+        None => find_in_groups(&mut recipe.ingredient_groups, name),
+    }
+    .ok_or_else(|| VariantError::IngredientNotFound(name.to_owned()))
+}
+
+/// Removes the first ingredient with the given name, returns `true` if there was one.
+fn remove_ingredient(
+    ingredients: &mut Vec<Ingredient>,
+    groups: &mut [IngredientGroup],
+    name: &str,
+) -> bool {
+    if let Some(index) = ingredients.iter().position(|i| matches(i, name)) {
+        ingredients.remove(index);
+        return true;
+    }
+    groups
+        .iter_mut()
+        .any(|group| remove_ingredient(&mut group.ingredients, &mut group.ingredient_groups, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{ErrorKind, ParseOptions};
+
+    const SRC: &str = "\
+# Cake
+
+---
+
+- *200 g* flour
+- *100 g* butter
+- *2* eggs
+
+## Topping
+
+- *200 ml* cream
+
+## Variant: vegan
+
+- *80 ml* vegetable oil instead of butter
+- without eggs
+- *100 ml* oat cream statt Cream
+
+## Variante: Glutenfrei
+
+- *200 g* rice flour instead of flour
+
+## Variant: vegan
+
+- *1 tbsp* flaxseed
+
+---
+
+Bake it.
+";
+
+    fn names(recipe: &Recipe) -> Vec<String> {
+        recipe
+            .all_ingredients()
+            .map(|i| match &i.amount {
+                Some(amount) => format!("{amount} {}", i.name),
+                None => i.name.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resolve_variants() {
+        let options = ParseOptions::new().extension(VariantExtension);
+        let recipe = Recipe::parse_with_options(SRC, &options).unwrap();
+
+        assert_eq!(
+            recipe.variants().collect::<Vec<_>>(),
+            ["vegan", "Glutenfrei"]
+        );
+        assert_eq!(recipe.ingredient_groups.len(), 1);
+        assert_eq!(
+            names(&recipe),
+            ["200 g flour", "100 g butter", "2 eggs", "200 ml cream"]
+        );
+
+        let vegan = recipe.variant("Vegan").unwrap();
+        assert_eq!(
+            names(&vegan),
+            [
+                "200 g flour",
+                "80 ml vegetable oil",
+                "1 tbsp flaxseed",
+                "100 ml oat cream"
+            ]
+        );
+        assert_eq!(vegan.variants().count(), 0);
+        assert_eq!(vegan.instructions, recipe.instructions);
+
+        let gluten_free = recipe.variant("glutenfrei").unwrap();
+        assert_eq!(
+            names(&gluten_free),
+            ["200 g rice flour", "100 g butter", "2 eggs", "200 ml cream"]
+        );
+    }
+
+    #[test]
+    fn variant_errors() {
+        let options = ParseOptions::new().extension(VariantExtension);
+        let recipe = Recipe::parse_with_options(SRC, &options).unwrap();
+        assert_eq!(
+            recipe.variant("keto").unwrap_err(),
+            VariantError::VariantNotFound("keto".to_owned())
+        );
+
+        let src = SRC.replace("without eggs", "without milk");
+        let recipe = Recipe::parse_with_options(&src, &options).unwrap();
+        assert_eq!(
+            recipe.variant("vegan").unwrap_err(),
+            VariantError::IngredientNotFound("milk".to_owned())
+        );
+
+        let src = SRC.replace("## Variante: Glutenfrei", "## Variant:");
+        let error = Recipe::parse_with_options(&src, &options).unwrap_err();
+        assert_eq!(
+            error.kind,
+            ErrorKind::InvalidExtensionData("variant is missing a name".to_owned())
+        );
+    }
+
+    #[test]
+    fn without_extension() {
+        let recipe = Recipe::parse(SRC).unwrap();
+        assert_eq!(recipe.variants().count(), 0);
+        assert_eq!(recipe.ingredient_groups.len(), 4);
+        assert_eq!(recipe.ingredient_groups[1].title, "Variant: vegan");
+    }
+}